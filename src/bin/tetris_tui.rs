@@ -0,0 +1,390 @@
+//! Plays the game in a terminal, with ANSI colors and raw mode keyboard input, for machines
+//! without a display.
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
+    style::{Color, Print, Stylize},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use egui::Color32;
+use std::io::{self, Write};
+use std::time::Instant;
+
+use tetris_gui::clock::{format_time, FRAME_TIME};
+use tetris_gui::game::EndOfGame;
+use tetris_gui::input::PAUSE_KEY;
+use tetris_gui::mode::{GameMode, SPLIT_LINES};
+use tetris_gui::options::LaunchOptions;
+use tetris_gui::pieces::{get_piece_display, PieceShape};
+use tetris_gui::records::SprintResult;
+use tetris_gui::save::SaveError;
+use tetris_gui::session::Session;
+
+/// Rows drawn for each piece in the queue and the hold slot.
+const PREVIEW_ROWS: i32 = 2;
+
+const BLOCK: &str = "██";
+const GHOST: &str = "░░";
+const EMPTY: &str = "  ";
+
+fn main() -> io::Result<()> {
+    let options = parse_options();
+    let game = options.start_game(options.config.clone());
+
+    let terminal = RawTerminal::enter()?;
+
+    let mut app = TuiApp {
+        session: Session::new(game),
+        preview_count: options.preview_count,
+        reports_releases: terminal.reports_releases,
+        last_lines: Vec::new(),
+        save_errors: Vec::new(),
+    };
+
+    let result = app.run();
+
+    // Errors can only be read once the terminal is back to normal.
+    drop(terminal);
+
+    for err in app.save_errors {
+        eprintln!("{err}");
+    }
+
+    result
+}
+
+fn parse_options() -> LaunchOptions {
+    let mut options = LaunchOptions::new(3);
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_default();
+
+        if let Err(err) = options.apply_flag(&arg, &value) {
+            eprintln!("{err}");
+        }
+    }
+
+    options
+}
+
+/// Keeps the terminal in raw mode on the alternate screen, restoring it when dropped, even
+/// on a panic.
+struct RawTerminal {
+    /// Whether the terminal tells when keys are let go, which held keys need.
+    reports_releases: bool,
+}
+
+impl RawTerminal {
+    fn enter() -> io::Result<RawTerminal> {
+        terminal::enable_raw_mode()?;
+        execute!(
+            io::stdout(),
+            EnterAlternateScreen,
+            Hide,
+            Clear(ClearType::All)
+        )?;
+
+        let reports_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+
+        if reports_releases {
+            execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+
+        Ok(RawTerminal { reports_releases })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        if self.reports_releases {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
+
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct TuiApp {
+    session: Session,
+    preview_count: usize,
+    reports_releases: bool,
+    /// What is on screen, so only the lines that change get redrawn.
+    last_lines: Vec<String>,
+    /// Replays and records that failed to save, printed once the game is closed.
+    save_errors: Vec<SaveError>,
+}
+
+impl TuiApp {
+    fn run(&mut self) -> io::Result<()> {
+        let mut last_frame = Instant::now();
+
+        loop {
+            let deadline = last_frame + FRAME_TIME;
+
+            while event::poll(deadline.saturating_duration_since(Instant::now()))? {
+                if let Event::Key(key_event) = event::read()? {
+                    if is_quit(&key_event) {
+                        return Ok(());
+                    }
+
+                    self.handle_key(key_event);
+                }
+            }
+
+            let time_now = Instant::now();
+            let frame_time = time_now.duration_since(last_frame);
+            last_frame = time_now;
+
+            let save_errors = self.session.advance(frame_time);
+            self.save_errors.extend(save_errors);
+
+            self.draw()?;
+        }
+    }
+
+    /// Passes the key on to the session by the name it goes by in egui. Terminals that do
+    /// not report key releases get a tap for every press and key repeat instead.
+    fn handle_key(&mut self, key_event: KeyEvent) {
+        let Some(name) = key_name(key_event.code) else {
+            return;
+        };
+
+        match (key_event.kind, self.reports_releases) {
+            (KeyEventKind::Release, _) => self.session.handle_key(&name, false),
+            (_, true) => self.session.handle_key(&name, true),
+            (_, false) => self.session.tap_key(&name),
+        }
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        let lines = self.render();
+        let mut stdout = io::stdout();
+
+        for (row, line) in lines.iter().enumerate() {
+            if self.last_lines.get(row) != Some(line) {
+                queue!(
+                    stdout,
+                    MoveTo(0, row as u16),
+                    Print(line),
+                    Clear(ClearType::UntilNewLine)
+                )?;
+            }
+        }
+
+        stdout.flush()?;
+        self.last_lines = lines;
+
+        Ok(())
+    }
+
+    /// The board with its ghost piece, framed, with the side panel to its right.
+    fn render(&self) -> Vec<String> {
+        let config = self.session.game.config();
+        let side_panel = self.render_side_panel();
+
+        let mut cells =
+            vec![vec![EMPTY.to_string(); config.width as usize]; config.visible_height as usize];
+
+        let mut paint = |coord: &[i32; 2], text: &str, color: Color| {
+            let row = coord[1] - config.buffer_height;
+
+            if row >= 0 && coord[0] >= 0 && coord[0] < config.width {
+                if let Some(line) = cells.get_mut(row as usize) {
+                    line[coord[0] as usize] = text.with(color).to_string();
+                }
+            }
+        };
+
+        if let Some(projection) = self.session.game.get_projection() {
+            for coord in projection.coords.iter() {
+                paint(coord, GHOST, to_terminal_color(projection.color));
+            }
+        }
+
+        for square in self.session.game.list_squares() {
+            paint(&square.0, BLOCK, to_terminal_color(square.1));
+        }
+
+        let border = |left: &str, right: &str| {
+            format!("{left}{}{right}", "─".repeat(2 * config.width as usize))
+        };
+
+        let mut lines = vec![border("┌", "┐")];
+        lines.extend(cells.iter().map(|row| format!("│{}│", row.concat())));
+        lines.push(border("└", "┘"));
+
+        // Lets the side panel run past the bottom of the board.
+        let board_width = 2 * config.width as usize + 2;
+        lines.resize(lines.len().max(side_panel.len()), " ".repeat(board_width));
+
+        for (row, line) in lines.iter_mut().enumerate() {
+            if let Some(side) = side_panel.get(row) {
+                line.push_str("  ");
+                line.push_str(side);
+            }
+        }
+
+        lines
+    }
+
+    fn render_side_panel(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("SCORE {}", self.session.game.score),
+            format!("LEVEL {}", self.session.game.get_level()),
+            format!("LINES {}", self.session.game.get_lines_cleared()),
+            format!("SEED  {}", self.session.game.get_seed()),
+            // Counts down in modes with a time limit.
+            format!(
+                "TIME  {}",
+                format_time(
+                    self.session
+                        .game
+                        .get_time_left()
+                        .unwrap_or(self.session.game.get_time())
+                )
+            ),
+        ];
+
+        if let Some(lines_left) = self.session.game.get_lines_left() {
+            lines.push(format!("LEFT  {lines_left}"));
+        }
+
+        if self.session.game.get_garbage_cleared() > 0 {
+            lines.push(format!("GARB  {}", self.session.game.get_garbage_cleared()));
+        }
+
+        lines.extend([String::new(), "NEXT".to_string()]);
+
+        for shape in self.session.game.get_next_pieces(self.preview_count) {
+            lines.extend(self.render_preview(shape, true));
+        }
+
+        lines.push("HOLD".to_string());
+
+        match self.session.game.get_held_piece() {
+            Some(shape) => lines.extend(self.render_preview(shape, self.session.game.can_hold())),
+            None => lines.extend(vec![String::new(); PREVIEW_ROWS as usize + 1]),
+        }
+
+        if let Some(lock_result) = self.session.game.get_last_lock_result() {
+            lines.extend(lock_result.get_labels());
+        }
+
+        if let Some(end_of_game) = self.session.game_over {
+            lines.push(String::new());
+
+            match end_of_game {
+                EndOfGame::GoalReached => lines.push("FINISHED".bold().to_string()),
+                EndOfGame::TimeUp => lines.push("TIME UP".bold().to_string()),
+                _ => {
+                    lines.push("GAME OVER".bold().to_string());
+                    lines.push(end_of_game.to_string());
+                }
+            }
+
+            lines.extend(self.render_sprint_result());
+            lines.push("ESC TO QUIT".to_string());
+        } else if self.session.is_paused {
+            lines.push(String::new());
+            lines.push("PAUSED".bold().to_string());
+        }
+
+        lines
+    }
+
+    /// Splits of a sprint and how it compares to the personal best.
+    fn render_sprint_result(&self) -> Vec<String> {
+        if self.session.game.config().mode != GameMode::Sprint {
+            return Vec::new();
+        }
+
+        let mut lines: Vec<String> = self
+            .session
+            .game
+            .get_splits()
+            .iter()
+            .enumerate()
+            .map(|(index, split)| {
+                format!(
+                    "{:>3}   {}",
+                    (index as u32 + 1) * SPLIT_LINES,
+                    format_time(*split)
+                )
+            })
+            .collect();
+
+        match &self.session.sprint_result {
+            Some(sprint_result) if sprint_result.is_new_best() => {
+                lines.push("NEW BEST".bold().to_string())
+            }
+            Some(SprintResult {
+                previous_best: Some(best),
+                ..
+            }) => lines.push(format!("BEST  {}", format_time(best.time))),
+            _ => {}
+        }
+
+        lines
+    }
+
+    /// A piece in its spawn orientation followed by a blank line, greyed out if disabled.
+    fn render_preview(&self, shape: PieceShape, is_enabled: bool) -> Vec<String> {
+        let piece = get_piece_display(shape, self.session.game.config().rotation_system.get());
+        let color = match is_enabled {
+            true => to_terminal_color(piece.color),
+            false => Color::DarkGrey,
+        };
+
+        let mut lines: Vec<String> = (0..PREVIEW_ROWS)
+            .map(|row| {
+                (0..piece.get_size()[0])
+                    .map(|col| match piece.coords.contains(&[col, row]) {
+                        true => BLOCK.with(color).to_string(),
+                        false => EMPTY.to_string(),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        lines.push(String::new());
+
+        lines
+    }
+}
+
+fn is_quit(key_event: &KeyEvent) -> bool {
+    key_event.code == KeyCode::Esc
+        || (key_event.code == KeyCode::Char('c')
+            && key_event.modifiers.contains(KeyModifiers::CONTROL))
+}
+
+/// The egui name of a key, which is what the shared key bindings go by.
+fn key_name(code: KeyCode) -> Option<String> {
+    match code {
+        KeyCode::Left => Some("Left".to_string()),
+        KeyCode::Right => Some("Right".to_string()),
+        KeyCode::Up => Some("Up".to_string()),
+        KeyCode::Down => Some("Down".to_string()),
+        KeyCode::Char(' ') => Some(PAUSE_KEY.to_string()),
+        KeyCode::Char(c) => Some(c.to_ascii_uppercase().to_string()),
+        _ => None,
+    }
+}
+
+fn to_terminal_color(color: Color32) -> Color {
+    Color::Rgb {
+        r: color.r(),
+        g: color.g(),
+        b: color.b(),
+    }
+}
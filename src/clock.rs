@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use crate::gravity::FRAMES_PER_SECOND;
+
+/// Length of one `Game::tick`.
+pub const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / FRAMES_PER_SECOND as u64);
+
+/// Whole frames closest to `duration`, so delays given in milliseconds land on the frame
+/// they would on a 60 Hz console.
+pub fn to_frames(duration: Duration) -> u32 {
+    (duration.as_secs_f64() * FRAMES_PER_SECOND as f64).round() as u32
+}
+
+/// Time taken by `frames` frames. Worked out from the frame rate rather than the rounded
+/// down `FRAME_TIME`, so a second's worth of frames is a whole second.
+pub fn to_duration(frames: u64) -> Duration {
+    Duration::from_nanos((frames as u128 * 1_000_000_000 / FRAMES_PER_SECOND as u128) as u64)
+}
+
+/// `duration` as minutes, seconds and milliseconds, like `1:23.456`.
+pub fn format_time(duration: Duration) -> String {
+    let millis = duration.as_millis();
+
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Frames a single call to `FrameClock::advance` may catch up on. Past that, after a stall
+/// or a suspended window, the game slows down instead of jumping ahead.
+const MAX_FRAMES_PER_ADVANCE: u32 = 10;
+
+/// Turns the time between a frontend's repaints into whole frames, carrying the rest over
+/// to the next repaint.
+#[derive(Default)]
+pub struct FrameClock {
+    leftover: Duration,
+}
+
+impl FrameClock {
+    /// Frames to tick for `elapsed` more real time.
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.leftover += elapsed;
+
+        let mut frames = 0;
+
+        while self.leftover >= FRAME_TIME && frames < MAX_FRAMES_PER_ADVANCE {
+            self.leftover -= FRAME_TIME;
+            frames += 1;
+        }
+
+        if frames == MAX_FRAMES_PER_ADVANCE {
+            self.leftover = Duration::ZERO;
+        }
+
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_seconds_of_frames_are_whole_seconds() {
+        assert_eq!(to_duration(3600), Duration::from_secs(60));
+        assert_eq!(format_time(to_duration(7200)), "2:00.000");
+        assert_eq!(to_frames(to_duration(7200)), 7200);
+    }
+
+    #[test]
+    fn the_frame_clock_carries_leftover_time_over() {
+        let mut clock = FrameClock::default();
+
+        assert_eq!(clock.advance(FRAME_TIME / 2), 0);
+        assert_eq!(clock.advance(FRAME_TIME), 1);
+        assert_eq!(
+            clock.advance(Duration::from_secs(1)),
+            MAX_FRAMES_PER_ADVANCE
+        );
+    }
+}
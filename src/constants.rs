@@ -1,12 +1,11 @@
+use serde::{Deserialize, Serialize};
+
 pub const SCALE: i32 = 40;
 
-pub const GAME_WIDTH: i32 = 400;
 pub const NEXT_PIECE_DISPLAY_WIDTH: i32 = 240;
 pub const HEIGHT: i32 = 800;
 
-pub const FPS: f32 = 3.;
-
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Movement {
     UP,
     RIGHT,
@@ -14,7 +13,7 @@ pub enum Movement {
     LEFT,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Rotation {
     CW,
     CCW,
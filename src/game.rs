@@ -1,322 +1,1289 @@
 use egui::Color32;
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::fmt;
-
-const QUEUE_INITIAL_LENGTH: usize = 3;
+use std::time::Duration;
 
 use crate::{
-    constants::{Movement, Rotation, GAME_WIDTH, HEIGHT, SCALE},
-    pieces::{get_next_piece_display, spawn_piece_above, Piece, PieceShape, NUMBER_OF_SHAPES},
+    clock::{to_duration, to_frames},
+    constants::{Movement, Rotation},
+    garbage::{get_attack, GarbageGenerator},
+    gravity::{SpeedCurve, LINES_PER_LEVEL, MAX_GRAVITY},
+    input::{Handling, HeldKeys, Input},
+    mode::{GameMode, DIG_ROWS, SPLIT_LINES},
+    pieces::{Piece, PieceShape, NUMBER_OF_SHAPES},
+    randomizer::{AnyRandomizer, Randomizer, RandomizerKind},
+    replay::Replay,
+    rotation::RotationSystemKind,
+    scoring::{detect_t_spin, LockResult, Scoring, TSpin},
 };
 
+/// Dimensions of the playfield, in cells, and the rules pieces follow in it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameConfig {
+    pub width: i32,
+    pub visible_height: i32,
+    /// Rows above the visible playfield where pieces spawn and may be pushed into.
+    pub buffer_height: i32,
+    pub rotation_system: RotationSystemKind,
+    pub randomizer: RandomizerKind,
+    /// How long a grounded piece may still slide or spin before it locks.
+    pub lock_delay: Duration,
+    /// Moves and rotations that may restart the lock delay before the piece drops a row.
+    pub max_lock_resets: u32,
+    /// Pause between a piece locking and the next one appearing.
+    pub entry_delay: Duration,
+    pub handling: Handling,
+    pub mode: GameMode,
+    /// Chance, from 0 to 1, that a row of garbage has its hole somewhere else than the row
+    /// above it.
+    pub garbage_messiness: f32,
+    pub speed_curve: SpeedCurve,
+    pub starting_level: u32,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            width: 10,
+            visible_height: 20,
+            buffer_height: 20,
+            rotation_system: RotationSystemKind::Srs,
+            randomizer: RandomizerKind::SevenBag,
+            lock_delay: Duration::from_millis(500),
+            max_lock_resets: 15,
+            entry_delay: Duration::ZERO,
+            handling: Handling::default(),
+            mode: GameMode::Endless,
+            garbage_messiness: 1.,
+            speed_curve: SpeedCurve::Guideline,
+            starting_level: 1,
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn total_height(&self) -> i32 {
+        self.visible_height + self.buffer_height
+    }
+
+    /// Whether games under both configs play by the same rules. Handling is left out, as
+    /// it is the player's preference.
+    pub fn has_same_rules(&self, other: &GameConfig) -> bool {
+        GameConfig {
+            handling: other.handling.clone(),
+            ..self.clone()
+        } == *other
+    }
+
+    /// Bottom row of a freshly spawned piece, right above the visible playfield when the
+    /// buffer allows it. Pieces then drop one row as they spawn, so they show right away.
+    pub fn spawn_row(&self) -> i32 {
+        (self.buffer_height - 1).max(1)
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct ColoredPoint(pub [i32; 2], pub Color32);
 
+/// Frozen cells of the playfield, addressed by `[column, row]` with row 0 at the top.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Board {
+    width: i32,
+    height: i32,
+    cells: Vec<Option<Color32>>,
+}
+
+impl Board {
+    pub fn new(width: i32, height: i32) -> Board {
+        Board {
+            width,
+            height,
+            cells: vec![None; (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn index(&self, col: i32, row: i32) -> Option<usize> {
+        if col < 0 || col >= self.width || row < 0 || row >= self.height {
+            None
+        } else {
+            Some((row * self.width + col) as usize)
+        }
+    }
+
+    pub fn get(&self, col: i32, row: i32) -> Option<Color32> {
+        self.index(col, row).and_then(|i| self.cells[i])
+    }
+
+    pub fn set(&mut self, col: i32, row: i32, color: Option<Color32>) {
+        if let Some(i) = self.index(col, row) {
+            self.cells[i] = color;
+        }
+    }
+
+    /// Whether a piece may occupy the cell. Rows above the top of the board are free.
+    pub fn is_free(&self, col: i32, row: i32) -> bool {
+        if col < 0 || col >= self.width || row >= self.height {
+            return false;
+        }
+
+        row < 0 || self.get(col, row).is_none()
+    }
+
+    pub fn collides(&self, piece: &Piece) -> bool {
+        piece
+            .coords
+            .iter()
+            .any(|coord| !self.is_free(coord[0], coord[1]))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().all(|cell| cell.is_none())
+    }
+
+    pub fn is_row_full(&self, row: i32) -> bool {
+        (0..self.width).all(|col| self.get(col, row).is_some())
+    }
+
+    pub fn full_rows(&self) -> Vec<i32> {
+        (0..self.height)
+            .filter(|row| self.is_row_full(*row))
+            .collect()
+    }
+
+    /// Removes the given rows and shifts everything above them down.
+    pub fn clear_rows(&mut self, rows: &[i32]) {
+        let mut cells = vec![None; self.cells.len()];
+        let mut target = self.height - 1;
+
+        for row in (0..self.height).rev() {
+            if rows.contains(&row) {
+                continue;
+            }
+
+            let (from, to) = ((row * self.width) as usize, (target * self.width) as usize);
+            let width = self.width as usize;
+            cells[to..to + width].copy_from_slice(&self.cells[from..from + width]);
+
+            target -= 1;
+        }
+
+        self.cells = cells;
+    }
+
+    /// Moves every row up by one to make room for `row` at the bottom, returning whether
+    /// any filled cell was pushed off the top.
+    pub fn push_up(&mut self, row: &[Option<Color32>]) -> bool {
+        let width = self.width as usize;
+        let is_pushed_out = self.cells[..width].iter().any(|cell| cell.is_some());
+
+        self.cells.drain(..width);
+        self.cells.extend(row.iter().copied().take(width));
+        self.cells.resize((self.width * self.height) as usize, None);
+
+        is_pushed_out
+    }
+
+    pub fn lock(&mut self, piece: &Piece) {
+        for coord in piece.coords.iter() {
+            self.set(coord[0], coord[1], Some(piece.color));
+        }
+    }
+
+    pub fn squares(&self) -> Vec<ColoredPoint> {
+        let mut squares = Vec::new();
+
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if let Some(color) = self.get(col, row) {
+                    squares.push(ColoredPoint([col, row], color));
+                }
+            }
+        }
+
+        squares
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Game {
-    pub frozen_squares: Vec<ColoredPoint>,
+    pub board: Board,
+    config: GameConfig,
     pub player_piece: Piece,
     pub score: i32,
+    lines_cleared: u32,
+    scoring: Scoring,
+    last_lock_result: Option<LockResult>,
+    /// Kick used by the last rotation, if the player piece has not moved since.
+    last_rotation_kick: Option<usize>,
     piece_queue: VecDeque<PieceShape>,
+    randomizer: AnyRandomizer,
+    seed: u64,
+    rng: Pcg64,
+    held_piece: Option<PieceShape>,
+    can_hold: bool,
+    /// Frames the player piece has spent grounded since the lock delay last restarted.
+    lock_frames: u32,
+    lock_resets: u32,
+    lowest_row: i32,
+    /// Rows of gravity owed to the player piece, carried over between frames.
+    pending_rows: f32,
+    held_keys: HeldKeys,
+    /// Frames since the last lock, while waiting for the next piece to enter.
+    entry_frames: Option<u32>,
+    /// Frames played so far.
+    frame: u64,
+    /// Frame on which every `SPLIT_LINES` lines were reached.
+    splits: Vec<u64>,
+    garbage: GarbageGenerator,
+    /// Rows of garbage at the bottom of the board. Clears only ever bring rows down onto
+    /// them, so they stay together under the rest of the stack.
+    garbage_height: u32,
+    garbage_cleared: u32,
+    /// Lines of garbage sent by opponents and not yet on the board, in batches sharing a
+    /// hole, oldest first.
+    incoming_garbage: VecDeque<u32>,
+    attack_sent: u32,
+    replay: Replay,
+    #[serde(skip)]
+    events: Vec<GameEvent>,
 }
 
-pub fn new_game() -> Game {
-    let random_shape: PieceShape = rand::random();
+/// Stream of the generator garbage holes are drawn from, apart from the one pieces are.
+const GARBAGE_STREAM: u128 = 1;
+
+pub fn new_game(config: GameConfig) -> Game {
+    new_game_with_seed(config, rand::random())
+}
 
-    Game {
-        frozen_squares: Vec::new(),
-        player_piece: spawn_piece_above(random_shape),
+/// Starts a game whose pieces come from `seed`. Two games with the same seed, config and
+/// inputs play out identically.
+pub fn new_game_with_seed(config: GameConfig, seed: u64) -> Game {
+    let mut rng = Pcg64::seed_from_u64(seed);
+    let mut randomizer = config.randomizer.build();
+
+    let first_shape = randomizer.next_shape(&mut rng);
+    let player_piece = config.rotation_system.get().spawn(first_shape, &config);
+    let piece_queue = (0..NUMBER_OF_SHAPES)
+        .map(|_| randomizer.next_shape(&mut rng))
+        .collect();
+    let replay = Replay::new(seed, config.clone());
+    // Garbage draws from its own stream, so it does not change the pieces a seed deals.
+    let garbage = GarbageGenerator::new(
+        Pcg64::new(seed as u128, GARBAGE_STREAM),
+        config.garbage_messiness,
+    );
+
+    let mut game = Game {
+        board: Board::new(config.width, config.total_height()),
+        config,
+        lowest_row: player_piece.get_bottom(),
+        player_piece: player_piece.clone(),
         score: 0,
-        piece_queue: VecDeque::from(PieceShape::generate_fair_collection(QUEUE_INITIAL_LENGTH)),
-    }
+        lines_cleared: 0,
+        scoring: Scoring::default(),
+        last_lock_result: None,
+        last_rotation_kick: None,
+        piece_queue,
+        randomizer,
+        seed,
+        rng,
+        held_piece: None,
+        can_hold: true,
+        lock_frames: 0,
+        lock_resets: 0,
+        pending_rows: 0.,
+        held_keys: HeldKeys::default(),
+        entry_frames: None,
+        frame: 0,
+        splits: Vec::new(),
+        garbage,
+        garbage_height: 0,
+        garbage_cleared: 0,
+        incoming_garbage: VecDeque::new(),
+        attack_sent: 0,
+        replay,
+        events: Vec::new(),
+    };
+
+    // An empty board always has room for the first rows of a dig and the first piece.
+    let _ = game.top_up_dig_garbage();
+    let _ = game.replace_player_piece(player_piece);
+
+    game
 }
 
-enum Outcome {
-    DoNothing,
-    Stick,
-    Free,
+/// Something that happened in the game, in the order it happened. Taken out of the game
+/// with `Game::drain_events`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    /// A new player piece entered the board, from the queue or from hold.
+    Spawned(PieceShape),
+    /// The player moved the piece. Gravity does not count.
+    Moved(Movement),
+    /// The piece turned, using the kick at `kick_index` in the rotation system's list.
+    Rotated {
+        rotation: Rotation,
+        kick_index: usize,
+    },
+    HardDropped {
+        rows: i32,
+    },
+    /// The piece went into hold.
+    Held(PieceShape),
+    Locked(LockResult),
+    /// Rows that were full after a lock, as they were numbered before being cleared.
+    LinesCleared(Vec<i32>),
+    LevelUp(u32),
+    /// Lines of garbage to send to opponents, left over after cancelling incoming garbage.
+    Attack(u32),
+    /// Incoming garbage was pushed up from the bottom of the board.
+    GarbageReceived(u32),
+    GameOver(EndOfGame),
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum StepKind<T, R> {
     Move(T),
     Rotate(R),
-    GoDown,
     HardDrop,
-}
-
-enum SoftDropEnd {
-    Yes,
-    No,
+    Hold,
 }
 
 impl Game {
-    pub fn step(
-        &mut self,
-        step_kind: StepKind<Option<Movement>, Rotation>,
-    ) -> Result<(), EndOfGame> {
+    /// Plays one frame of `FRAME_TIME`: the inputs given since the last frame, in order,
+    /// then auto shift, gravity, the lock delay and the entry delay. Every frontend drives
+    /// the game through here, so it plays the same at any frame rate.
+    pub fn tick(&mut self, inputs: &[Input]) -> Result<(), EndOfGame> {
+        for input in inputs {
+            self.replay.record(self.frame, *input);
+        }
+
+        self.frame += 1;
+        self.replay.frame_count = self.frame;
+
+        let result = self.play_frame(inputs).and_then(|_| self.check_mode_end());
+
+        self.record_end_of_game(result)
+    }
+
+    /// Lets go of every held key, as a game resumed from a save starts with none down. The
+    /// releases are recorded on the next frame, so the replay lets go of them too.
+    pub(crate) fn release_held_keys(&mut self) {
+        for key in self.held_keys.get_held() {
+            self.replay.record(self.frame, Input::Release(key));
+            self.held_keys.release(key);
+        }
+    }
+
+    fn play_frame(&mut self, inputs: &[Input]) -> Result<(), EndOfGame> {
+        if let Some(entry_frames) = self.entry_frames {
+            let entry_frames = entry_frames + 1;
+
+            if entry_frames < to_frames(self.config.entry_delay) {
+                self.entry_frames = Some(entry_frames);
+            } else {
+                self.entry_frames = None;
+                self.spawn_next_piece()?;
+            }
+        }
+
+        for input in inputs {
+            match *input {
+                Input::Step(step_kind) if self.has_player_piece() => self.step(step_kind)?,
+                Input::Step(_) => {}
+                Input::Garbage(lines) => self.incoming_garbage.push_back(lines),
+                // Held keys are followed even while waiting for the next piece, so a shift
+                // charged during the entry delay carries over to it.
+                input => self.hold_key(input),
+            }
+        }
+
+        if self.entry_frames.is_some() {
+            self.held_keys.advance(&self.config.handling);
+            return Ok(());
+        }
+
+        self.auto_shift();
+        self.apply_gravity();
+        self.update_lock_delay()
+    }
+
+    /// Presses or releases a held key, shifting or soft dropping once on the press.
+    fn hold_key(&mut self, input: Input) {
+        let movement = match input {
+            Input::Press(key) => self.held_keys.press(key),
+            Input::Release(key) => {
+                self.held_keys.release(key);
+                None
+            }
+            Input::Step(_) | Input::Garbage(_) => None,
+        };
+
+        if let (Some(movement), true) = (movement, self.has_player_piece()) {
+            self.player_move(movement);
+        }
+    }
+
+    /// Slides the player piece while left or right is held past the delayed auto shift.
+    fn auto_shift(&mut self) {
+        let Some((movement, shifts)) = self.held_keys.advance(&self.config.handling) else {
+            return;
+        };
+
+        for _ in 0..shifts {
+            if !self.player_move(movement) {
+                break;
+            }
+        }
+    }
+
+    /// Moves the piece for the player, who scores a point for every row soft dropped.
+    fn player_move(&mut self, movement: Movement) -> bool {
+        let has_moved = self.move_piece(Some(movement));
+
+        if has_moved {
+            self.events.push(GameEvent::Moved(movement));
+
+            if movement == Movement::DOWN {
+                self.score += 1
+            }
+        }
+
+        has_moved
+    }
+
+    fn step(&mut self, step_kind: StepKind<Option<Movement>, Rotation>) -> Result<(), EndOfGame> {
         match step_kind {
-            StepKind::GoDown => self.force_piece_down_or_stick()?,
-            StepKind::Move(movement) => {
-                let is_soft_drop_end = self.move_piece(movement);
-                if let SoftDropEnd::Yes = is_soft_drop_end {
-                    self.score += self.player_piece.coords.len() as i32
-                }
+            StepKind::Move(Some(movement)) => {
+                self.player_move(movement);
+                Ok(())
             }
-            StepKind::Rotate(rotation) => self.rotate_piece(rotation),
-            StepKind::HardDrop => {
-                self.score += 2 * self.player_piece.coords.len() as i32;
-                self.drop_down()
+            StepKind::Move(None) => Ok(()),
+            StepKind::Rotate(rotation) => {
+                self.rotate_piece(rotation);
+                Ok(())
             }
+            StepKind::HardDrop => self.drop_down(),
+            StepKind::Hold => self.hold_piece(),
         }
+    }
 
-        let full_lines_heights = self.get_full_lines_heights();
+    /// Events since the last call, oldest first.
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
 
-        if !full_lines_heights.is_empty() {
-            let n = full_lines_heights.len() as i32;
-            self.score += n.pow(2) * 100;
-            self.erase_lines(full_lines_heights);
+    fn record_end_of_game(&mut self, result: Result<(), EndOfGame>) -> Result<(), EndOfGame> {
+        if let Err(end_of_game) = result {
+            self.events.push(GameEvent::GameOver(end_of_game));
+        }
+
+        result
+    }
+
+    /// Drops the player piece by the whole rows of gravity accumulated so far, so sub-row
+    /// gravity spreads over several frames and 20G crosses the board in one. Holding soft
+    /// drop multiplies gravity by the soft drop factor.
+    fn apply_gravity(&mut self) {
+        let is_soft_dropping = self.held_keys.is_soft_dropping();
+
+        self.pending_rows += match is_soft_dropping {
+            true => (self.get_gravity() * self.config.handling.soft_drop_factor).min(MAX_GRAVITY),
+            false => self.get_gravity(),
         };
 
+        while self.pending_rows >= 1. {
+            self.pending_rows -= 1.;
+
+            let has_moved = match is_soft_dropping {
+                true => self.player_move(Movement::DOWN),
+                false => self.move_piece(Some(Movement::DOWN)),
+            };
+
+            if !has_moved {
+                self.pending_rows = 0.;
+            }
+        }
+    }
+
+    /// Runs the lock delay of a grounded piece for one more frame, locking the piece once
+    /// the delay is over.
+    fn update_lock_delay(&mut self) -> Result<(), EndOfGame> {
+        if !self.is_grounded() {
+            self.lock_frames = 0;
+            return Ok(());
+        }
+
+        self.lock_frames += 1;
+
+        if self.lock_frames >= to_frames(self.config.lock_delay) {
+            self.lock_piece(self.player_piece.clone())?;
+        }
+
         Ok(())
     }
 
-    fn rotate_piece(&mut self, rotation: Rotation) {
+    fn is_grounded(&self) -> bool {
         let mut phantom_piece = self.player_piece.clone();
+        phantom_piece.step_down();
 
-        match rotation {
-            Rotation::CCW => phantom_piece.rotate_ccw(),
-            Rotation::CW => phantom_piece.rotate_cw(),
+        self.board.collides(&phantom_piece)
+    }
+
+    /// Restarts a running lock delay after a successful move or rotation, until the piece
+    /// runs out of resets. Reaching a new lowest row gives all of them back.
+    fn reset_lock_delay(&mut self) {
+        let bottom = self.player_piece.get_bottom();
+
+        if bottom > self.lowest_row {
+            self.lowest_row = bottom;
+            self.lock_resets = 0;
+            self.lock_frames = 0;
+        } else if self.lock_frames > 0 && self.lock_resets < self.config.max_lock_resets {
+            self.lock_resets += 1;
+            self.lock_frames = 0;
+        }
+    }
+
+    /// Locks `piece` into the board, scores it and spawns the next piece. Every way a
+    /// piece can lock goes through here, so it is also where the game ends: a piece
+    /// sticking out of the top of the board tops out, a piece locking entirely above the
+    /// visible playfield locks out and a spawn overlapping the stack blocks out.
+    fn lock_piece(&mut self, piece: Piece) -> Result<(), EndOfGame> {
+        let t_spin = match self.last_rotation_kick {
+            Some(kick_index) => detect_t_spin(&piece, &self.board, kick_index),
+            None => TSpin::None,
         };
 
-        self.get_rotation_result(&phantom_piece);
+        let is_top_out = piece.coords.iter().any(|coord| coord[1] < 0);
+        let is_lock_out = piece
+            .coords
+            .iter()
+            .all(|coord| coord[1] < self.config.buffer_height);
 
-        if let Some(piece) = self.get_rotation_result(&phantom_piece) {
-            self.player_piece = piece;
+        self.board.lock(&piece);
+
+        let full_lines_heights = self.get_full_lines_heights();
+        let lines = full_lines_heights.len() as u32;
+        let garbage_top = self.board.height() - self.garbage_height as i32;
+        let garbage_lines = full_lines_heights
+            .iter()
+            .filter(|row| **row >= garbage_top)
+            .count() as u32;
+
+        if !full_lines_heights.is_empty() {
+            self.erase_lines(full_lines_heights.clone());
+        };
+
+        let level = self.get_level();
+        let perfect_clear = lines > 0 && self.board.is_empty();
+        let lock_result = self.scoring.score_lock(lines, t_spin, perfect_clear, level);
+
+        self.score += lock_result.points;
+        self.lines_cleared += lines;
+        self.garbage_height -= garbage_lines;
+        self.garbage_cleared += garbage_lines;
+
+        while (self.splits.len() as u32 + 1) * SPLIT_LINES <= self.lines_cleared {
+            self.splits.push(self.frame);
         }
-    }
+        self.events.push(GameEvent::Locked(lock_result.clone()));
+        self.send_attack(get_attack(&lock_result));
+        self.last_lock_result = Some(lock_result);
 
-    fn get_rotation_result(&self, phantom_piece: &Piece) -> Option<Piece> {
-        for kick in phantom_piece.piece_shape.get_kicks() {
-            let kicked_piece = phantom_piece.kick(kick);
+        if !full_lines_heights.is_empty() {
+            self.events
+                .push(GameEvent::LinesCleared(full_lines_heights));
+        }
 
-            if kicked_piece.intersect(
-                &self
-                    .frozen_squares
-                    .iter()
-                    .map(|colored_point| colored_point.0)
-                    .collect(),
-            ) || kicked_piece.hits_sides()
-                || kicked_piece.hits_bottom()
-            {
-                continue;
-            } else {
-                return Some(kicked_piece);
-            }
+        if self.get_level() > level {
+            self.events.push(GameEvent::LevelUp(self.get_level()));
         }
 
-        None
+        self.check_mode_end()?;
+
+        if is_top_out {
+            return Err(EndOfGame::TopOut);
+        }
+
+        if is_lock_out {
+            return Err(EndOfGame::LockOut);
+        }
+
+        self.top_up_dig_garbage()?;
+
+        // Clearing lines holds incoming garbage back until a lock that clears none.
+        if lines == 0 {
+            self.add_incoming_garbage()?;
+        }
+
+        if !self.config.entry_delay.is_zero() {
+            self.entry_frames = Some(0);
+            return Ok(());
+        }
+
+        self.spawn_next_piece()
     }
 
-    fn move_piece(&mut self, movement: Option<Movement>) -> SoftDropEnd {
-        let mut phantom_piece = self.player_piece.clone();
+    /// Pushes up rows of garbage until a dig has as many on the board as it has left to
+    /// clear, up to `DIG_ROWS`, or half the visible playfield on small boards.
+    fn top_up_dig_garbage(&mut self) -> Result<(), EndOfGame> {
+        let GameMode::Dig { lines } = self.config.mode else {
+            return Ok(());
+        };
 
-        let mut outcome: Outcome = Outcome::Free;
+        let rows = DIG_ROWS
+            .min(self.config.visible_height as u32 / 2)
+            .min(lines.saturating_sub(self.garbage_cleared))
+            .saturating_sub(self.garbage_height);
 
-        phantom_piece.make_move(movement);
+        self.add_garbage(rows)
+    }
+
+    /// Pushes `rows` rows of garbage up from the bottom of the board, topping out if the
+    /// stack goes past the top.
+    fn add_garbage(&mut self, rows: u32) -> Result<(), EndOfGame> {
+        let is_pushed_out = self.garbage.push_rows(&mut self.board, rows);
+
+        self.raise_garbage(rows, is_pushed_out)
+    }
+
+    /// Pushes a batch of `rows` rows of incoming garbage, all with the same hole.
+    fn add_garbage_batch(&mut self, rows: u32) -> Result<(), EndOfGame> {
+        let is_pushed_out = self.garbage.push_batch(&mut self.board, rows);
 
-        if phantom_piece.hits_bottom() {
-            outcome = Outcome::Stick
+        self.raise_garbage(rows, is_pushed_out)
+    }
+
+    fn raise_garbage(&mut self, rows: u32, is_pushed_out: bool) -> Result<(), EndOfGame> {
+        self.garbage_height = (self.garbage_height + rows).min(self.board.height() as u32);
+
+        if is_pushed_out {
+            return Err(EndOfGame::TopOut);
         }
 
-        if phantom_piece.intersect(
-            &self
-                .frozen_squares
-                .iter()
-                .map(|colored_point| colored_point.0)
-                .collect(),
-        ) {
-            match movement {
-                Some(Movement::DOWN) => outcome = Outcome::Stick,
-                _ => outcome = Outcome::DoNothing,
+        Ok(())
+    }
+
+    /// Spends `attack` lines cancelling incoming garbage, oldest first, and sends the rest
+    /// to opponents as a `GameEvent::Attack`.
+    fn send_attack(&mut self, mut attack: u32) {
+        while let (true, Some(batch)) = (attack > 0, self.incoming_garbage.front_mut()) {
+            let cancelled = attack.min(*batch);
+
+            *batch -= cancelled;
+            attack -= cancelled;
+
+            if *batch == 0 {
+                self.incoming_garbage.pop_front();
             }
         }
 
-        if phantom_piece.hits_sides() {
-            outcome = Outcome::DoNothing;
+        if attack > 0 {
+            self.attack_sent += attack;
+            self.events.push(GameEvent::Attack(attack));
         }
+    }
 
-        match outcome {
-            Outcome::Free => {
-                self.player_piece = phantom_piece;
+    /// Pushes every incoming batch of garbage up from the bottom of the board.
+    fn add_incoming_garbage(&mut self) -> Result<(), EndOfGame> {
+        while let Some(lines) = self.incoming_garbage.pop_front() {
+            self.events.push(GameEvent::GarbageReceived(lines));
+            self.add_garbage_batch(lines)?;
+        }
 
-                SoftDropEnd::No
-            }
-            Outcome::Stick => {
-                self.frozen_squares.extend(
-                    self.player_piece
-                        .coords
-                        .iter()
-                        .map(|coord| ColoredPoint(*coord, phantom_piece.color)),
-                );
+        Ok(())
+    }
+
+    /// Ends the game if the rules of its mode say it is over.
+    fn check_mode_end(&self) -> Result<(), EndOfGame> {
+        match self.config.mode.get_end_of_game(self) {
+            Some(end_of_game) => Err(end_of_game),
+            None => Ok(()),
+        }
+    }
+
+    /// Swaps the player piece with the held one, at most once per piece.
+    fn hold_piece(&mut self) -> Result<(), EndOfGame> {
+        if !self.can_hold {
+            return Ok(());
+        }
+
+        let current_shape = self.player_piece.piece_shape;
+
+        let piece = match self.held_piece {
+            Some(held_shape) => self.spawn_piece(held_shape),
+            None => self.pop_next_piece(),
+        };
+        self.held_piece = Some(current_shape);
+        self.can_hold = false;
+        self.events.push(GameEvent::Held(current_shape));
+
+        self.replace_player_piece(piece)?;
+
+        Ok(())
+    }
+
+    fn rotate_piece(&mut self, rotation: Rotation) {
+        let phantom_piece = self
+            .config
+            .rotation_system
+            .get()
+            .rotate(&self.player_piece, rotation);
+
+        if let Some((piece, kick_index)) = self.get_rotation_result(&phantom_piece) {
+            self.player_piece = piece;
+            self.reset_lock_delay();
+            self.last_rotation_kick = Some(kick_index);
+            self.events.push(GameEvent::Rotated {
+                rotation,
+                kick_index,
+            });
+        }
+    }
 
-                self.player_piece = self.pop_next_piece();
+    fn get_rotation_result(&self, phantom_piece: &Piece) -> Option<(Piece, usize)> {
+        let kicks = self.config.rotation_system.get().get_kicks(
+            &self.player_piece,
+            phantom_piece,
+            &self.board,
+        );
 
-                SoftDropEnd::Yes
+        for (kick_index, kick) in kicks.into_iter().enumerate() {
+            let kicked_piece = phantom_piece.kick(kick);
+
+            if !self.board.collides(&kicked_piece) {
+                return Some((kicked_piece, kick_index));
             }
-            _ => SoftDropEnd::No,
         }
+
+        None
     }
 
-    fn force_piece_down_or_stick(&mut self) -> Result<(), EndOfGame> {
+    fn move_piece(&mut self, movement: Option<Movement>) -> bool {
+        // Up and no movement leave the piece where it is, so they must not count as a move
+        // that resets the lock delay or spoils a T-spin.
+        if matches!(movement, Some(Movement::UP) | None) {
+            return false;
+        }
+
         let mut phantom_piece = self.player_piece.clone();
-        let mut outcome: Outcome = Outcome::Free;
 
-        phantom_piece.step_down();
+        phantom_piece.make_move(movement);
 
-        if phantom_piece.hits_bottom() {
-            outcome = Outcome::Stick
+        if self.board.collides(&phantom_piece) {
+            return false;
         }
 
-        if phantom_piece.intersect(
-            &self
-                .frozen_squares
-                .iter()
-                .map(|colored_point| colored_point.0)
-                .collect(),
-        ) {
-            outcome = Outcome::Stick;
-        }
-
-        match outcome {
-            Outcome::Stick => {
-                self.frozen_squares.extend(
-                    self.player_piece
-                        .coords
-                        .iter()
-                        .map(|coord| ColoredPoint(*coord, phantom_piece.color)),
-                );
-
-                let old_piece = self.player_piece.clone();
-                self.player_piece = self.pop_next_piece();
-
-                if self.player_piece.intersect(&old_piece.coords) {
-                    return Err(EndOfGame);
-                }
-            }
-            Outcome::Free => {
-                self.player_piece = phantom_piece;
-            }
-            Outcome::DoNothing => {
-                self.player_piece = phantom_piece;
-            }
+        self.player_piece = phantom_piece;
+        self.reset_lock_delay();
+        self.last_rotation_kick = None;
+
+        true
+    }
+
+    fn drop_down(&mut self) -> Result<(), EndOfGame> {
+        let projection = self.get_landing();
+        let rows_dropped = projection.get_bottom() - self.player_piece.get_bottom();
+
+        self.score += 2 * rows_dropped;
+
+        if rows_dropped > 0 {
+            self.last_rotation_kick = None;
         }
 
-        Ok(())
+        self.events
+            .push(GameEvent::HardDropped { rows: rows_dropped });
+
+        self.lock_piece(projection)
     }
 
-    fn drop_down(&mut self) {
+    /// Where the player piece would land if hard dropped, unless it has yet to enter.
+    pub fn get_projection(&self) -> Option<Piece> {
+        self.has_player_piece().then(|| self.get_landing())
+    }
+
+    fn get_landing(&self) -> Piece {
         let mut phantom_piece = self.player_piece.clone();
 
         loop {
             let mut next_phantom_piece = phantom_piece.clone();
             next_phantom_piece.step_down();
 
-            if next_phantom_piece.hits_bottom()
-                || next_phantom_piece.intersect(
-                    &self
-                        .frozen_squares
-                        .iter()
-                        .map(|colored_point| colored_point.0)
-                        .collect(),
-                )
-            {
-                self.frozen_squares.extend(
-                    phantom_piece
-                        .coords
-                        .iter()
-                        .map(|coord| ColoredPoint(*coord, phantom_piece.color)),
-                );
-
-                self.player_piece = self.pop_next_piece();
-
-                break;
-            } else {
-                phantom_piece = next_phantom_piece;
+            if self.board.collides(&next_phantom_piece) {
+                return phantom_piece;
             }
+
+            phantom_piece = next_phantom_piece;
         }
     }
 
+    /// False during the entry delay, between a lock and the next piece appearing.
+    pub fn has_player_piece(&self) -> bool {
+        self.entry_frames.is_none()
+    }
+
     pub fn list_squares(&self) -> Vec<ColoredPoint> {
-        let mut colored_points = self.frozen_squares.clone();
+        let mut colored_points = self.board.squares();
 
-        for coord in self.player_piece.coords.iter() {
-            colored_points.push(ColoredPoint(*coord, self.player_piece.color))
+        if self.has_player_piece() {
+            for coord in self.player_piece.coords.iter() {
+                colored_points.push(ColoredPoint(*coord, self.player_piece.color))
+            }
         }
 
         colored_points
     }
 
     fn get_full_lines_heights(&self) -> Vec<i32> {
-        let mut full_lines_heights = Vec::new();
+        self.board.full_rows()
+    }
 
-        for i in 0..HEIGHT / SCALE {
-            let frozen_squares = self.frozen_squares.iter();
+    fn erase_lines(&mut self, lines: Vec<i32>) {
+        self.board.clear_rows(&lines);
+    }
 
-            let line_i = frozen_squares.filter(|tup| tup.0[1] == (SCALE * i));
+    fn spawn_piece(&self, piece_shape: PieceShape) -> Piece {
+        self.config
+            .rotation_system
+            .get()
+            .spawn(piece_shape, &self.config)
+    }
 
-            if line_i.count() == (GAME_WIDTH / SCALE) as usize {
-                full_lines_heights.push(SCALE * i);
-            }
-        }
+    fn spawn_next_piece(&mut self) -> Result<(), EndOfGame> {
+        let piece = self.pop_next_piece();
+        self.can_hold = true;
 
-        full_lines_heights
+        self.replace_player_piece(piece)
     }
 
-    fn erase_lines(&mut self, lines: Vec<i32>) {
-        let mut new_squares = Vec::new();
+    /// Makes `piece` the player piece, with a fresh lock delay. Blocks out if the piece
+    /// has nowhere to spawn.
+    fn replace_player_piece(&mut self, mut piece: Piece) -> Result<(), EndOfGame> {
+        let is_block_out = self.board.collides(&piece);
+        let dropped = piece.kick([0, 1]);
 
-        for square in self.frozen_squares.iter() {
-            if !lines.contains(&square.0[1]) {
-                let lines_below = lines.iter().filter(|x| **x >= square.0[1]).count();
+        // As in the guideline, the piece drops a row on the frame it spawns when it can.
+        if !is_block_out && !self.board.collides(&dropped) {
+            piece = dropped;
+        }
 
-                new_squares.push(ColoredPoint(
-                    [square.0[0], square.0[1] + SCALE * lines_below as i32],
-                    square.1,
-                ))
-            }
+        self.last_rotation_kick = None;
+        self.lowest_row = piece.get_bottom();
+        self.lock_frames = 0;
+        self.lock_resets = 0;
+        self.pending_rows = 0.;
+        self.events.push(GameEvent::Spawned(piece.piece_shape));
+        self.player_piece = piece;
+
+        if is_block_out {
+            return Err(EndOfGame::BlockOut);
         }
 
-        self.frozen_squares = new_squares;
+        Ok(())
     }
 
     fn pop_next_piece(&mut self) -> Piece {
-        let new_piece_shape = self.piece_queue.pop_front();
+        let new_piece_shape = self.piece_queue.pop_front().unwrap();
+
+        self.piece_queue
+            .push_back(self.randomizer.next_shape(&mut self.rng));
+
+        self.spawn_piece(new_piece_shape)
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn get_frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Game time so far, a whole number of frames.
+    pub fn get_time(&self) -> Duration {
+        to_duration(self.frame)
+    }
+
+    /// Game time left before the time limit, in modes with one.
+    pub fn get_time_left(&self) -> Option<Duration> {
+        self.config.mode.get_time_limit().map(|time_limit| {
+            to_duration((to_frames(time_limit) as u64).saturating_sub(self.frame))
+        })
+    }
+
+    /// Game time at which every `SPLIT_LINES` lines were reached.
+    pub fn get_splits(&self) -> Vec<Duration> {
+        self.splits
+            .iter()
+            .map(|frame| to_duration(*frame))
+            .collect()
+    }
+
+    /// Everything needed to play this game again, up to now.
+    pub fn get_replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn get_lines_cleared(&self) -> u32 {
+        self.lines_cleared
+    }
+
+    /// Lines of garbage cleared, out of all the lines cleared.
+    pub fn get_garbage_cleared(&self) -> u32 {
+        self.garbage_cleared
+    }
+
+    /// Lines of garbage sent by opponents that have not reached the board yet.
+    pub fn get_incoming_garbage(&self) -> u32 {
+        self.incoming_garbage.iter().sum()
+    }
+
+    /// Lines of garbage sent to opponents, after cancelling incoming garbage.
+    pub fn get_attack_sent(&self) -> u32 {
+        self.attack_sent
+    }
 
-        if self.piece_queue.len() < (2 * NUMBER_OF_SHAPES) as usize {
-            self.piece_queue
-                .append(&mut VecDeque::from(PieceShape::generate_fair_collection(3)))
+    /// Lines still to clear to win, in modes with a line goal. Digs only count garbage.
+    pub fn get_lines_left(&self) -> Option<u32> {
+        let lines_cleared = match self.config.mode {
+            GameMode::Dig { .. } => self.garbage_cleared,
+            _ => self.lines_cleared,
+        };
+
+        self.config
+            .mode
+            .get_line_goal()
+            .map(|goal| goal.saturating_sub(lines_cleared))
+    }
+
+    /// Goes up by one every `LINES_PER_LEVEL` lines, up to the last level of the mode.
+    pub fn get_level(&self) -> u32 {
+        let level = self.config.starting_level + self.lines_cleared / LINES_PER_LEVEL;
+
+        match self.config.mode.get_max_level() {
+            Some(max_level) => level.min(max_level.max(self.config.starting_level)),
+            None => level,
         }
+    }
 
-        spawn_piece_above(new_piece_shape.unwrap())
+    /// Rows per frame the player piece should fall at, from the speed curve.
+    pub fn get_gravity(&self) -> f32 {
+        self.config.speed_curve.get_gravity(self.get_level())
     }
 
-    pub fn get_next_piece(&self) -> Piece {
-        let next_piece_shape = *self.piece_queue.front().unwrap();
+    /// Scoring breakdown of the most recent lock.
+    pub fn get_last_lock_result(&self) -> Option<&LockResult> {
+        self.last_lock_result.as_ref()
+    }
+
+    pub fn get_held_piece(&self) -> Option<PieceShape> {
+        self.held_piece
+    }
 
-        get_next_piece_display(next_piece_shape)
+    pub fn can_hold(&self) -> bool {
+        self.can_hold
+    }
+
+    /// The next `n` shapes to be spawned, in order. The queue always holds at least
+    /// `NUMBER_OF_SHAPES` of them.
+    pub fn get_next_pieces(&self, n: usize) -> Vec<PieceShape> {
+        self.piece_queue.iter().take(n).copied().collect()
     }
 }
 
-#[derive(Debug)]
-pub struct EndOfGame;
+/// How a game ended: won, or lost in one of the guideline's three ways of topping out.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EndOfGame {
+    /// The goal of the game mode was met.
+    GoalReached,
+    /// The time limit of the game mode ran out.
+    TimeUp,
+    /// A new piece spawned overlapping the stack.
+    BlockOut,
+    /// A piece locked entirely above the visible playfield.
+    LockOut,
+    /// Part of the stack was pushed above the top of the board.
+    TopOut,
+}
 
 impl fmt::Display for EndOfGame {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Game over.")
+        match self {
+            EndOfGame::GoalReached => write!(f, "Goal reached."),
+            EndOfGame::TimeUp => write!(f, "Time up."),
+            EndOfGame::BlockOut => write!(f, "Block out."),
+            EndOfGame::LockOut => write!(f, "Lock out."),
+            EndOfGame::TopOut => write!(f, "Top out."),
+        }
     }
 }
 
 impl std::error::Error for EndOfGame {}
 
-pub fn read_u8() -> Result<u8, EndOfGame> {
-    Err(EndOfGame)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::HeldKey;
+    use crate::replay::ReplayPlayer;
+
+    /// Shifts, spins, soft drops, holds and hard drops on a fixed schedule.
+    fn scripted_inputs(frame: u64) -> Vec<Input> {
+        match frame % 40 {
+            5 => vec![Input::Press(HeldKey::Left)],
+            12 => vec![Input::Release(HeldKey::Left)],
+            18 if frame % 120 == 18 => vec![Input::Step(StepKind::Hold)],
+            18 => vec![Input::Step(StepKind::Rotate(Rotation::CW))],
+            25 => vec![Input::Press(HeldKey::SoftDrop)],
+            30 => vec![
+                Input::Release(HeldKey::SoftDrop),
+                Input::Step(StepKind::HardDrop),
+            ],
+            _ => Vec::new(),
+        }
+    }
+
+    fn play_scripted(seed: u64, frames: u64) -> Game {
+        let mut game = new_game_with_seed(GameConfig::default(), seed);
+
+        for frame in 0..frames {
+            if game.tick(&scripted_inputs(frame)).is_err() {
+                break;
+            }
+        }
+
+        game
+    }
+
+    fn to_json(game: &Game) -> String {
+        serde_json::to_string(game).unwrap()
+    }
+
+    fn fill(board: &mut Board, cols: std::ops::Range<i32>, rows: std::ops::Range<i32>) {
+        for row in rows {
+            for col in cols.clone() {
+                board.set(col, row, Some(Color32::GRAY));
+            }
+        }
+    }
+
+    /// Frames until the player piece locks, tapping `input` on every frame.
+    fn frames_to_lock(game: &mut Game, input: Option<Input>) -> u32 {
+        game.drain_events();
+
+        for frame in 1..=1000 {
+            game.tick(&Vec::from_iter(input)).unwrap();
+
+            let has_locked = game
+                .drain_events()
+                .iter()
+                .any(|event| matches!(event, GameEvent::Locked(_)));
+
+            if has_locked {
+                return frame;
+            }
+        }
+
+        panic!("the piece never locked");
+    }
+
+    #[test]
+    fn same_seed_and_inputs_play_out_identically() {
+        let game = play_scripted(42, 2000);
+
+        assert!(game.score > 0);
+        assert_eq!(to_json(&game), to_json(&play_scripted(42, 2000)));
+        assert_ne!(to_json(&game), to_json(&play_scripted(43, 2000)));
+    }
+
+    #[test]
+    fn replay_plays_back_the_same_game() {
+        let game = play_scripted(7, 1500);
+        let (replayed, _) = game.get_replay().play();
+
+        assert_eq!(to_json(&game), to_json(&replayed));
+    }
+
+    #[test]
+    fn seeking_back_matches_playing_from_the_start() {
+        // Left to gravity alone, a couple of pieces lock in this time.
+        let mut replay = Replay::new(0, GameConfig::default());
+        replay.frame_count = 3000;
+
+        let mut player = ReplayPlayer::new(replay.clone());
+        player.seek(3000);
+        player.seek(1300);
+        player.seek_previous_lock();
+
+        let mut fresh_player = ReplayPlayer::new(replay);
+        fresh_player.seek(1300);
+        fresh_player.seek_previous_lock();
+
+        assert!(player.get_frame() > 0);
+        assert_eq!(player.get_frame(), fresh_player.get_frame());
+        assert_eq!(to_json(player.game()), to_json(fresh_player.game()));
+    }
+
+    #[test]
+    fn pieces_show_on_the_frame_they_spawn() {
+        let mut game = new_game_with_seed(GameConfig::default(), 0);
+
+        for shape in PieceShape::ALL {
+            game.replace_player_piece(game.spawn_piece(shape)).unwrap();
+
+            assert_eq!(game.player_piece.get_bottom(), game.config.buffer_height);
+        }
+    }
+
+    #[test]
+    fn lock_delay_resets_are_capped() {
+        let mut game = new_game_with_seed(GameConfig::default(), 0);
+        game.player_piece = game.get_landing();
+
+        let lock_delay = to_frames(game.config.lock_delay);
+        let mut inputs = [Movement::LEFT, Movement::RIGHT]
+            .into_iter()
+            .cycle()
+            .map(|movement| Input::Step(StepKind::Move(Some(movement))));
+        let mut frames = 0;
+
+        game.drain_events();
+
+        while !game
+            .drain_events()
+            .iter()
+            .any(|event| matches!(event, GameEvent::Locked(_)))
+        {
+            game.tick(&[inputs.next().unwrap()]).unwrap();
+            frames += 1;
+            assert!(frames < 1000, "the piece never locked");
+        }
+
+        assert_eq!(frames, game.config.max_lock_resets + lock_delay);
+    }
+
+    #[test]
+    fn up_does_not_reset_the_lock_delay() {
+        let mut game = new_game_with_seed(GameConfig::default(), 0);
+        game.player_piece = game.get_landing();
+        game.last_rotation_kick = Some(2);
+
+        game.tick(&[Input::Step(StepKind::Move(Some(Movement::UP)))])
+            .unwrap();
+
+        assert_eq!(game.last_rotation_kick, Some(2));
+        assert!(!game
+            .drain_events()
+            .iter()
+            .any(|event| matches!(event, GameEvent::Moved(_))));
+
+        let up = Input::Step(StepKind::Move(Some(Movement::UP)));
+
+        assert_eq!(
+            frames_to_lock(&mut game, Some(up)),
+            to_frames(game.config.lock_delay) - 1
+        );
+    }
+
+    #[test]
+    fn spawning_into_the_stack_blocks_out() {
+        let mut game = new_game_with_seed(GameConfig::default(), 0);
+        let height = game.board.height();
+
+        fill(&mut game.board, 1..10, 0..height);
+
+        let piece = game.spawn_piece(PieceShape::T);
+
+        assert_eq!(game.replace_player_piece(piece), Err(EndOfGame::BlockOut));
+    }
+
+    #[test]
+    fn locking_above_the_visible_playfield_locks_out() {
+        let mut game = new_game_with_seed(GameConfig::default(), 0);
+        let (buffer, height) = (game.config.buffer_height, game.board.height());
+
+        fill(&mut game.board, 1..10, buffer..height);
+        game.player_piece = game.spawn_piece(PieceShape::T);
+
+        assert_eq!(
+            game.tick(&[Input::Step(StepKind::HardDrop)]),
+            Err(EndOfGame::LockOut)
+        );
+    }
+
+    #[test]
+    fn locking_above_the_board_tops_out() {
+        let config = GameConfig {
+            buffer_height: 0,
+            ..GameConfig::default()
+        };
+        let mut game = new_game_with_seed(config, 0);
+        let height = game.board.height();
+
+        fill(&mut game.board, 1..10, 1..height);
+        game.player_piece = game.spawn_piece(PieceShape::T).kick([0, -2]);
+
+        assert_eq!(
+            game.tick(&[Input::Step(StepKind::HardDrop)]),
+            Err(EndOfGame::TopOut)
+        );
+    }
+
+    #[test]
+    fn attacks_cancel_incoming_garbage_before_being_sent() {
+        let mut game = new_game_with_seed(GameConfig::default(), 0);
+        game.incoming_garbage = VecDeque::from([3, 2]);
+
+        game.send_attack(4);
+
+        assert_eq!(game.incoming_garbage, [1]);
+        assert_eq!(game.get_attack_sent(), 0);
+
+        game.send_attack(3);
+
+        assert!(game.incoming_garbage.is_empty());
+        assert_eq!(game.get_attack_sent(), 2);
+        assert!(game.drain_events().contains(&GameEvent::Attack(2)));
+    }
+
+    #[test]
+    fn incoming_garbage_comes_up_on_a_lock_without_lines() {
+        let mut game = new_game_with_seed(GameConfig::default(), 0);
+
+        game.tick(&[Input::Garbage(2)]).unwrap();
+
+        assert_eq!(game.get_incoming_garbage(), 2);
+
+        game.tick(&[Input::Step(StepKind::HardDrop)]).unwrap();
+
+        let height = game.board.height();
+        let holes: Vec<_> = [height - 1, height - 2]
+            .iter()
+            .map(|row| {
+                (0..game.board.width())
+                    .filter(|col| game.board.get(*col, *row).is_none())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        assert_eq!(game.get_incoming_garbage(), 0);
+        assert!(game.drain_events().contains(&GameEvent::GarbageReceived(2)));
+        assert_eq!(holes[0].len(), 1);
+        assert_eq!(holes[0], holes[1]);
+    }
 }
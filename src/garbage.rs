@@ -0,0 +1,197 @@
+use egui::Color32;
+use rand::Rng;
+use rand_pcg::Pcg64;
+use serde::{Deserialize, Serialize};
+
+use crate::game::Board;
+use crate::scoring::{LockResult, TSpin};
+
+pub const GARBAGE_COLOR: Color32 = Color32::GRAY;
+
+/// Extra lines sent for each combo, the last one repeating for longer combos.
+const COMBO_ATTACK: [u32; 11] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+
+/// Lines a perfect clear sends, in place of everything else.
+const PERFECT_CLEAR_ATTACK: u32 = 10;
+
+/// Lines of garbage a lock sends to opponents.
+pub fn get_attack(lock_result: &LockResult) -> u32 {
+    if lock_result.lines == 0 {
+        return 0;
+    }
+
+    if lock_result.perfect_clear {
+        return PERFECT_CLEAR_ATTACK;
+    }
+
+    let base = match (lock_result.t_spin, lock_result.lines) {
+        (TSpin::None, 1) => 0,
+        (TSpin::None, 2) => 1,
+        (TSpin::None, 3) => 2,
+        (TSpin::None, _) => 4,
+        (TSpin::Mini, 1) => 0,
+        (TSpin::Mini, _) => 1,
+        (TSpin::Full, 1) => 2,
+        (TSpin::Full, 2) => 4,
+        (TSpin::Full, _) => 6,
+    };
+
+    let combo = COMBO_ATTACK[(lock_result.combo as usize).min(COMBO_ATTACK.len() - 1)];
+
+    base + lock_result.back_to_back as u32 + combo
+}
+
+/// Makes rows of garbage, each full but for one hole, and pushes them up from the bottom of
+/// the board.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GarbageGenerator {
+    rng: Pcg64,
+    /// Chance, from 0 to 1, that a row's hole is not under the one of the row above it.
+    messiness: f32,
+    /// Column of the hole in the last row made.
+    hole: Option<i32>,
+}
+
+impl GarbageGenerator {
+    pub fn new(rng: Pcg64, messiness: f32) -> GarbageGenerator {
+        GarbageGenerator {
+            rng,
+            messiness: messiness.clamp(0., 1.),
+            hole: None,
+        }
+    }
+
+    /// Adds `rows` rows of garbage at the bottom of `board`, returning whether the stack was
+    /// pushed off the top.
+    pub fn push_rows(&mut self, board: &mut Board, rows: u32) -> bool {
+        let mut is_pushed_out = false;
+
+        for _ in 0..rows {
+            let hole = self.next_hole(board.width());
+
+            is_pushed_out |= push_row(board, hole);
+        }
+
+        is_pushed_out
+    }
+
+    /// Adds `rows` rows of garbage sharing a single hole, as an opponent's attack arrives,
+    /// returning whether the stack was pushed off the top.
+    pub fn push_batch(&mut self, board: &mut Board, rows: u32) -> bool {
+        let hole = self.next_hole(board.width());
+
+        (0..rows).fold(false, |is_pushed_out, _| {
+            push_row(board, hole) | is_pushed_out
+        })
+    }
+
+    /// Keeps the last hole unless the row turns out messy, in which case the hole moves to
+    /// another column.
+    fn next_hole(&mut self, width: i32) -> i32 {
+        let hole = match self.hole {
+            Some(hole) if !self.rng.gen_bool(self.messiness as f64) => hole,
+            Some(hole) if width > 1 => {
+                let other = self.rng.gen_range(0..width - 1);
+
+                if other >= hole {
+                    other + 1
+                } else {
+                    other
+                }
+            }
+            _ => self.rng.gen_range(0..width),
+        };
+
+        self.hole = Some(hole);
+
+        hole
+    }
+}
+
+fn push_row(board: &mut Board, hole: i32) -> bool {
+    let row: Vec<_> = (0..board.width())
+        .map(|col| (col != hole).then_some(GARBAGE_COLOR))
+        .collect();
+
+    board.push_up(&row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn lock_result(lines: u32, t_spin: TSpin, back_to_back: bool, combo: u32) -> LockResult {
+        LockResult {
+            lines,
+            t_spin,
+            back_to_back,
+            combo,
+            perfect_clear: false,
+            points: 0,
+        }
+    }
+
+    fn holes(board: &Board, rows: i32) -> Vec<Vec<i32>> {
+        (board.height() - rows..board.height())
+            .map(|row| {
+                (0..board.width())
+                    .filter(|col| board.get(*col, row).is_none())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn attacks_follow_the_table() {
+        let attack = |lines, t_spin| get_attack(&lock_result(lines, t_spin, false, 0));
+
+        assert_eq!(
+            [1, 2, 3, 4].map(|lines| attack(lines, TSpin::None)),
+            [0, 1, 2, 4]
+        );
+        assert_eq!([1, 2].map(|lines| attack(lines, TSpin::Mini)), [0, 1]);
+        assert_eq!([1, 2, 3].map(|lines| attack(lines, TSpin::Full)), [2, 4, 6]);
+        assert_eq!(attack(0, TSpin::Full), 0);
+    }
+
+    #[test]
+    fn back_to_back_combos_and_perfect_clears_add_up() {
+        assert_eq!(get_attack(&lock_result(4, TSpin::None, true, 0)), 5);
+        assert_eq!(get_attack(&lock_result(2, TSpin::Full, true, 3)), 7);
+        assert_eq!(get_attack(&lock_result(1, TSpin::None, false, 50)), 5);
+
+        let perfect_clear = LockResult {
+            perfect_clear: true,
+            ..lock_result(1, TSpin::None, false, 0)
+        };
+
+        assert_eq!(get_attack(&perfect_clear), PERFECT_CLEAR_ATTACK);
+    }
+
+    #[test]
+    fn batches_share_one_hole() {
+        let mut board = Board::new(10, 20);
+        let mut garbage = GarbageGenerator::new(Pcg64::seed_from_u64(0), 1.);
+
+        garbage.push_batch(&mut board, 3);
+
+        let holes = holes(&board, 3);
+
+        assert_eq!(holes[0].len(), 1);
+        assert!(holes.iter().all(|row| *row == holes[0]));
+    }
+
+    #[test]
+    fn clean_garbage_keeps_its_hole() {
+        let mut board = Board::new(10, 20);
+        let mut garbage = GarbageGenerator::new(Pcg64::seed_from_u64(0), 0.);
+
+        assert!(!garbage.push_rows(&mut board, 8));
+
+        let holes = holes(&board, 8);
+
+        assert!(holes.iter().all(|row| *row == holes[0]));
+        assert!(garbage.push_rows(&mut board, 13));
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Gravity is measured in rows per frame at this rate, the unit the guideline calls G.
+pub const FRAMES_PER_SECOND: f32 = 60.;
+
+/// The guideline's 20G, fast enough for a piece to cross a standard 20 row playfield in a
+/// single frame. Taller playfields take more than one.
+pub const MAX_GRAVITY: f32 = 20.;
+
+pub const LINES_PER_LEVEL: u32 = 10;
+
+/// Highest level a game may start at. Games still go past it by clearing lines.
+pub const MAX_STARTING_LEVEL: u32 = 30;
+
+/// Last level the guideline formula is defined for. It is past `MAX_GRAVITY` by then, and
+/// further on its base goes negative.
+const GUIDELINE_LEVELS: u32 = 20;
+
+/// Frames per row on the NTSC NES, indexed by NES level, which starts at 0 where ours
+/// starts at 1. NES level 29 and above stay at one.
+const NES_FRAMES_PER_ROW: [u32; 30] = [
+    48, 43, 38, 33, 28, 23, 18, 13, 8, 6, 5, 5, 5, 4, 4, 4, 3, 3, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    1,
+];
+
+/// Maps a level to the speed pieces fall at.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub enum SpeedCurve {
+    /// `(0.8 - (level - 1) * 0.007) ^ (level - 1)` seconds per row, up to level 20.
+    #[default]
+    Guideline,
+    Nes,
+    /// Gravity for each level starting at level 1. Higher levels keep the last value. Kept
+    /// in the config, so saves and replays carry the table along.
+    Custom(Vec<f32>),
+}
+
+impl SpeedCurve {
+    /// Gravity at the given level, in rows per frame.
+    pub fn get_gravity(&self, level: u32) -> f32 {
+        let gravity = match self {
+            SpeedCurve::Guideline => {
+                let level = level.clamp(1, GUIDELINE_LEVELS) as f32;
+                let seconds_per_row = (0.8 - (level - 1.) * 0.007).powf(level - 1.);
+
+                1. / (seconds_per_row * FRAMES_PER_SECOND)
+            }
+            SpeedCurve::Nes => {
+                let index = (level.max(1) as usize - 1).min(NES_FRAMES_PER_ROW.len() - 1);
+
+                1. / NES_FRAMES_PER_ROW[index] as f32
+            }
+            SpeedCurve::Custom(table) => {
+                let index = (level.max(1) as usize - 1).min(table.len().saturating_sub(1));
+
+                table.get(index).copied().unwrap_or(0.)
+            }
+        };
+
+        gravity.min(MAX_GRAVITY)
+    }
+}
+
+/// Parses `guideline`, `nes`, or a list of gravities for a custom curve, separated by
+/// commas or whitespace, as in a file read by `--gravity-file`.
+/// Gravities must be above zero, or pieces would never fall.
+impl FromStr for SpeedCurve {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "guideline" => Ok(SpeedCurve::Guideline),
+            "nes" => Ok(SpeedCurve::Nes),
+            _ => s
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|value| !value.is_empty())
+                .map(|value| match value.trim().parse::<f32>() {
+                    Ok(gravity) if gravity > 0. => Ok(gravity),
+                    _ => Err(format!("Unknown speed curve: {s}")),
+                })
+                .collect::<Result<Vec<f32>, _>>()
+                .map(SpeedCurve::Custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nes_speeds_start_at_level_zero() {
+        assert_eq!(SpeedCurve::Nes.get_gravity(1), 1. / 48.);
+        assert_eq!(SpeedCurve::Nes.get_gravity(30), 1.);
+    }
+
+    #[test]
+    fn guideline_gravity_stays_at_the_cap_past_its_last_level() {
+        for level in [20, 116, 118, 150, 1000, u32::MAX] {
+            assert_eq!(SpeedCurve::Guideline.get_gravity(level), MAX_GRAVITY);
+        }
+
+        assert!(SpeedCurve::Guideline.get_gravity(1) > 0.);
+    }
+
+    #[test]
+    fn custom_curves_can_span_lines() {
+        assert_eq!(
+            "0.5,\n1\n 2 ".parse(),
+            Ok(SpeedCurve::Custom(vec![0.5, 1., 2.]))
+        );
+    }
+
+    #[test]
+    fn custom_curves_need_gravities_above_zero() {
+        assert_eq!("0.5, 1".parse(), Ok(SpeedCurve::Custom(vec![0.5, 1.])));
+
+        for curve in ["0", "-1", "NaN", "0.5,x"] {
+            assert!(curve.parse::<SpeedCurve>().is_err(), "{curve}");
+        }
+    }
+}
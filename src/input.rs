@@ -0,0 +1,343 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::clock::to_frames;
+use crate::constants::{Movement, Rotation};
+use crate::game::StepKind;
+
+/// Keys that keep acting for as long as they are held down.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum HeldKey {
+    Left,
+    Right,
+    SoftDrop,
+}
+
+/// What a frontend passes to `Game::tick`, in the order the player gave it.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Input {
+    /// Played once, whether the key is held or not.
+    Step(StepKind<Option<Movement>, Rotation>),
+    Press(HeldKey),
+    Release(HeldKey),
+    /// Lines of garbage sent by an opponent, added to the board on the next lock that
+    /// clears no lines unless attacks cancel them first.
+    Garbage(u32),
+}
+
+/// How held keys repeat. Part of the config, so replays play back with the handling they
+/// were recorded with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Handling {
+    /// Delayed auto shift: how long left or right is held before the piece starts sliding.
+    pub das: Duration,
+    /// Auto repeat rate: time between two shifts while sliding. Zero slides the piece
+    /// straight to the wall.
+    pub arr: Duration,
+    /// Gravity is multiplied by this while soft drop is held.
+    pub soft_drop_factor: f32,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            das: Duration::from_millis(167),
+            arr: Duration::from_millis(33),
+            soft_drop_factor: 20.,
+        }
+    }
+}
+
+/// Which held keys are down and how long the piece has been shifting for.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct HeldKeys {
+    left: bool,
+    right: bool,
+    soft_drop: bool,
+    /// The most recently pressed of the held directions.
+    shift: Option<Movement>,
+    /// Frames since `shift` was pressed.
+    shift_frames: u32,
+}
+
+impl HeldKeys {
+    /// Holds `key` down, returning the direction to shift right away if it starts a shift.
+    /// Pressing a key already held, as key repeat does, changes nothing.
+    pub(crate) fn press(&mut self, key: HeldKey) -> Option<Movement> {
+        let (is_held, direction) = match key {
+            HeldKey::Left => (&mut self.left, Movement::LEFT),
+            HeldKey::Right => (&mut self.right, Movement::RIGHT),
+            HeldKey::SoftDrop => (&mut self.soft_drop, Movement::DOWN),
+        };
+
+        if *is_held {
+            return None;
+        }
+
+        *is_held = true;
+
+        if key != HeldKey::SoftDrop {
+            self.shift = Some(direction);
+            self.shift_frames = 0;
+        }
+
+        Some(direction)
+    }
+
+    /// Lets go of `key`. Releasing the direction being shifted hands over to the other one
+    /// if it is still held, which starts its own delay.
+    pub(crate) fn release(&mut self, key: HeldKey) {
+        match key {
+            HeldKey::Left => self.left = false,
+            HeldKey::Right => self.right = false,
+            HeldKey::SoftDrop => self.soft_drop = false,
+        }
+
+        let shift = match (self.left, self.right) {
+            (true, false) => Some(Movement::LEFT),
+            (false, true) => Some(Movement::RIGHT),
+            (true, true) => self.shift,
+            (false, false) => None,
+        };
+
+        if shift != self.shift {
+            self.shift = shift;
+            self.shift_frames = 0;
+        }
+    }
+
+    /// Keys that are down, in no particular order.
+    pub(crate) fn get_held(&self) -> Vec<HeldKey> {
+        [
+            (self.left, HeldKey::Left),
+            (self.right, HeldKey::Right),
+            (self.soft_drop, HeldKey::SoftDrop),
+        ]
+        .into_iter()
+        .filter_map(|(is_held, key)| is_held.then_some(key))
+        .collect()
+    }
+
+    pub(crate) fn is_soft_dropping(&self) -> bool {
+        self.soft_drop
+    }
+
+    /// Runs the auto shift for one more frame, returning the direction and how many times
+    /// to shift in it. `u32::MAX` means as far as the piece goes.
+    pub(crate) fn advance(&mut self, handling: &Handling) -> Option<(Movement, u32)> {
+        let shift = self.shift?;
+
+        self.shift_frames += 1;
+
+        let das = to_frames(handling.das);
+        let arr = to_frames(handling.arr);
+
+        if self.shift_frames <= das {
+            return None;
+        }
+
+        if arr == 0 {
+            return Some((shift, u32::MAX));
+        }
+
+        (self.shift_frames - das - 1)
+            .is_multiple_of(arr)
+            .then_some((shift, 1))
+    }
+}
+
+/// Something the player can do, whatever key it is bound to.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Action {
+    Left,
+    Right,
+    SoftDrop,
+    Up,
+    RotateCW,
+    RotateCCW,
+    HardDrop,
+    Hold,
+}
+
+/// Name of the key that pauses and resumes the game.
+pub const PAUSE_KEY: &str = "Space";
+
+impl Action {
+    /// The action bound to the key called `name`, using egui's key names ("Left", "E", ...)
+    /// so every frontend shares the same bindings.
+    pub fn from_key_name(name: &str) -> Option<Action> {
+        match name {
+            "Left" => Some(Action::Left),
+            "Right" => Some(Action::Right),
+            "Down" => Some(Action::SoftDrop),
+            "Up" => Some(Action::Up),
+            "E" => Some(Action::RotateCW),
+            "Q" => Some(Action::RotateCCW),
+            "Z" => Some(Action::HardDrop),
+            "C" => Some(Action::Hold),
+            _ => None,
+        }
+    }
+
+    /// The input for pressing the key. Held keys also need one for letting it go.
+    fn press(self) -> Input {
+        match self {
+            Action::Left => Input::Press(HeldKey::Left),
+            Action::Right => Input::Press(HeldKey::Right),
+            Action::SoftDrop => Input::Press(HeldKey::SoftDrop),
+            Action::Up => Input::Step(StepKind::Move(Some(Movement::UP))),
+            Action::RotateCW => Input::Step(StepKind::Rotate(Rotation::CW)),
+            Action::RotateCCW => Input::Step(StepKind::Rotate(Rotation::CCW)),
+            Action::HardDrop => Input::Step(StepKind::HardDrop),
+            Action::Hold => Input::Step(StepKind::Hold),
+        }
+    }
+
+    fn release(self) -> Option<Input> {
+        match self.press() {
+            Input::Press(key) => Some(Input::Release(key)),
+            _ => None,
+        }
+    }
+}
+
+/// Key downs and key ups in the order the frontend saw them, waiting for the next tick.
+/// Every one of them is played, however many come between two frames, and key repeat on
+/// a key already down is ignored.
+#[derive(Default)]
+pub struct InputQueue {
+    inputs: Vec<Input>,
+    keys_down: Vec<Action>,
+}
+
+impl InputQueue {
+    pub fn key_down(&mut self, action: Action) {
+        if self.keys_down.contains(&action) {
+            return;
+        }
+
+        self.keys_down.push(action);
+        self.inputs.push(action.press());
+    }
+
+    /// Lets go of `action`. Keys that are not down, such as one pressed while the game
+    /// was paused, are ignored.
+    pub fn key_up(&mut self, action: Action) {
+        let Some(index) = self.keys_down.iter().position(|key| *key == action) else {
+            return;
+        };
+
+        self.keys_down.remove(index);
+        self.inputs.extend(action.release());
+    }
+
+    /// A key down followed by its key up, for frontends that cannot tell when keys are
+    /// let go.
+    pub fn tap(&mut self, action: Action) {
+        self.key_down(action);
+        self.key_up(action);
+    }
+
+    /// Everything queued since the last call, oldest first, for the next `Game::tick`.
+    pub fn take(&mut self) -> Vec<Input> {
+        std::mem::take(&mut self.inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames, counted from the press, on which `held_keys` shifts the piece.
+    fn shift_frames(held_keys: &mut HeldKeys, handling: &Handling) -> Vec<(u32, u32)> {
+        (1..=20)
+            .filter_map(|frame| {
+                held_keys
+                    .advance(handling)
+                    .map(|(_, shifts)| (frame, shifts))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shifts_repeat_every_arr_once_das_is_charged() {
+        let handling = Handling::default();
+        let mut held_keys = HeldKeys::default();
+
+        assert_eq!(held_keys.press(HeldKey::Left), Some(Movement::LEFT));
+        assert_eq!(
+            shift_frames(&mut held_keys, &handling),
+            [(11, 1), (13, 1), (15, 1), (17, 1), (19, 1)]
+        );
+    }
+
+    #[test]
+    fn zero_arr_shifts_to_the_wall() {
+        let handling = Handling {
+            arr: Duration::ZERO,
+            ..Handling::default()
+        };
+        let mut held_keys = HeldKeys::default();
+
+        held_keys.press(HeldKey::Right);
+
+        assert_eq!(shift_frames(&mut held_keys, &handling)[0], (11, u32::MAX));
+    }
+
+    #[test]
+    fn key_repeat_does_not_restart_the_shift() {
+        let handling = Handling::default();
+        let mut held_keys = HeldKeys::default();
+
+        held_keys.press(HeldKey::Left);
+
+        for _ in 0..5 {
+            held_keys.advance(&handling);
+        }
+
+        assert_eq!(held_keys.press(HeldKey::Left), None);
+        assert_eq!(shift_frames(&mut held_keys, &handling)[0], (6, 1));
+    }
+
+    #[test]
+    fn releasing_a_direction_hands_over_to_the_other_one() {
+        let handling = Handling::default();
+        let mut held_keys = HeldKeys::default();
+
+        held_keys.press(HeldKey::Left);
+        held_keys.press(HeldKey::Right);
+        held_keys.release(HeldKey::Right);
+
+        let shifts: Vec<_> = (0..11)
+            .filter_map(|_| held_keys.advance(&handling))
+            .collect();
+
+        assert_eq!(shifts, [(Movement::LEFT, 1)]);
+
+        held_keys.release(HeldKey::Left);
+
+        assert_eq!(held_keys.advance(&handling), None);
+        assert!(held_keys.get_held().is_empty());
+    }
+
+    #[test]
+    fn queued_inputs_keep_their_order() {
+        let mut input_queue = InputQueue::default();
+
+        input_queue.key_down(Action::Left);
+        input_queue.key_down(Action::Left);
+        input_queue.tap(Action::HardDrop);
+        input_queue.key_up(Action::Left);
+        input_queue.key_up(Action::SoftDrop);
+
+        assert_eq!(
+            input_queue.take(),
+            [
+                Input::Press(HeldKey::Left),
+                Input::Step(StepKind::HardDrop),
+                Input::Release(HeldKey::Left),
+            ]
+        );
+        assert!(input_queue.take().is_empty());
+    }
+}
@@ -1,3 +1,16 @@
+pub mod clock;
 pub mod constants;
 pub mod game;
+pub mod garbage;
+pub mod gravity;
+pub mod input;
+pub mod mode;
+pub mod options;
 pub mod pieces;
+pub mod randomizer;
+pub mod records;
+pub mod replay;
+pub mod rotation;
+pub mod save;
+pub mod scoring;
+pub mod session;
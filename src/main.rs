@@ -1,315 +1,774 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use egui::{Color32, Pos2, Rect, Stroke};
-use tetris_gui::constants::{
-    Movement, Rotation, FPS, GAME_WIDTH, HEIGHT, NEXT_PIECE_DISPLAY_WIDTH, SCALE,
+use egui::{Align2, Color32, FontId, Pos2, Rect, Stroke};
+use tetris_gui::clock::format_time;
+use tetris_gui::constants::{HEIGHT, NEXT_PIECE_DISPLAY_WIDTH, SCALE};
+use tetris_gui::game::{EndOfGame, Game, GameConfig};
+use tetris_gui::mode::{
+    GameMode, DIG_LINE_GOALS, MARATHON_LINES, SPLIT_LINES, SPRINT_LINES, ULTRA_TIME_LIMITS,
 };
-use tetris_gui::game::{new_game, EndOfGame, Game, StepKind};
-
+use tetris_gui::options::LaunchOptions;
+use tetris_gui::pieces::{get_piece_display, Piece};
+use tetris_gui::records::SprintResult;
+use tetris_gui::replay::{load_replay, Replay, ReplayPlayer};
+use tetris_gui::save::{load_game, save_game, SavedGame, SAVE_FILE};
+use tetris_gui::session::Session;
+
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
+/// Rows kept for the held piece in the side panel, enough for any shape.
+const HOLD_ROWS: i32 = 2;
+
+/// Room under the board for the replay viewer's controls.
+const REPLAY_CONTROLS_HEIGHT: f32 = 60.;
+
+const REPLAY_SPEEDS: [f32; 6] = [0.25, 0.5, 1., 2., 4., 8.];
+
+struct Options {
+    launch: LaunchOptions,
+    replay_path: Option<PathBuf>,
+    /// Whether to offer the mode menu, unless a mode was picked on the command line.
+    choose_mode: bool,
+}
+
 fn main() {
-    let options = eframe::NativeOptions {
+    let options = parse_options();
+
+    let replay = options
+        .replay_path
+        .as_ref()
+        .and_then(|path| match load_replay(path) {
+            Ok(replay) => Some(replay),
+            Err(err) => {
+                eprintln!("{err}");
+                None
+            }
+        });
+
+    let config = match &replay {
+        Some(replay) => &replay.config,
+        None => &options.launch.config,
+    };
+    let cell_size = get_cell_size(config);
+    let controls_height = match replay {
+        Some(_) => REPLAY_CONTROLS_HEIGHT,
+        None => 0.,
+    };
+
+    let native_options = eframe::NativeOptions {
         initial_window_size: Some(egui::vec2(
-            (GAME_WIDTH + NEXT_PIECE_DISPLAY_WIDTH) as f32,
-            HEIGHT as f32,
+            config.width as f32 * cell_size + NEXT_PIECE_DISPLAY_WIDTH as f32,
+            config.visible_height as f32 * cell_size + controls_height,
         )),
         ..Default::default()
     };
     eframe::run_native(
         "Quattorix",
-        options,
-        Box::new(|_cc| Box::<MyApp>::default()),
+        native_options,
+        Box::new(move |_cc| Box::new(MyApp::new(options, replay))),
     )
 }
 
-#[derive(Copy, Clone)]
-enum Command {
-    None,
-    Movement(Movement),
-    Rotation(Rotation),
-    DropDown,
+fn parse_options() -> Options {
+    let mut launch = LaunchOptions::new(1);
+    let mut replay_path = None;
+    let mut choose_mode = true;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_default();
+
+        match arg.as_str() {
+            "--replay" => replay_path = Some(PathBuf::from(value)),
+            _ => {
+                if arg == "--mode" {
+                    choose_mode = false;
+                }
+
+                if let Err(err) = launch.apply_flag(&arg, &value) {
+                    eprintln!("{err}");
+                }
+            }
+        }
+    }
+
+    Options {
+        launch,
+        replay_path,
+        choose_mode,
+    }
+}
+
+/// Cells are drawn at `SCALE` pixels unless the board would not fit in `HEIGHT`.
+fn get_cell_size(config: &GameConfig) -> f32 {
+    (SCALE as f32).min(HEIGHT as f32 / config.visible_height as f32)
 }
 
 struct MyApp {
-    game: Game,
-    time: Instant,
-    fine_grained_time: Instant,
-    current_move_command: Command,
-    current_rotation_command: Command,
-    is_paused: bool,
-    game_over: bool,
+    session: Session,
+    cell_size: f32,
+    options: LaunchOptions,
+    last_frame: Instant,
+    /// Game left over from the last session, until the player resumes it or starts over.
+    saved_game: Option<SavedGame>,
+    is_choosing_mode: bool,
+    /// Replay being watched instead of playing.
+    viewer: Option<ReplayViewer>,
 }
 
-impl Default for MyApp {
-    fn default() -> Self {
-        let now = Instant::now();
+impl MyApp {
+    fn new(options: Options, replay: Option<Replay>) -> Self {
         Self {
-            game: new_game(),
-            time: now,
-            fine_grained_time: now,
-            current_move_command: Command::None,
-            current_rotation_command: Command::None,
-            is_paused: false,
-            game_over: false,
+            cell_size: get_cell_size(match &replay {
+                Some(replay) => &replay.config,
+                None => &options.launch.config,
+            }),
+            session: Session::new(options.launch.start_game(options.launch.config.clone())),
+            options: options.launch,
+            last_frame: Instant::now(),
+            saved_game: match replay {
+                Some(_) => None,
+                None => load_autosave(),
+            },
+            is_choosing_mode: options.choose_mode && replay.is_none(),
+            viewer: replay.map(ReplayViewer::new),
+        }
+    }
+
+    /// The game on screen: the one being played, or the replay being watched.
+    fn shown_game(&self) -> &Game {
+        match &self.viewer {
+            Some(viewer) => viewer.player.game(),
+            None => &self.session.game,
+        }
+    }
+
+    fn resume(&mut self, saved_game: SavedGame) {
+        self.cell_size = get_cell_size(saved_game.game.config());
+        self.session = Session::new(saved_game.game);
+        self.session.is_paused = saved_game.is_paused;
+        self.is_choosing_mode = false;
+        self.last_frame = Instant::now();
+    }
+
+    /// Replaces the game with a new one in `mode`, keeping the rest of its config.
+    fn start_game(&mut self, mode: GameMode) {
+        let mut config = self.session.game.config().clone();
+        config.mode = mode;
+
+        self.session = Session::new(self.options.start_game(config));
+        self.is_choosing_mode = false;
+        self.last_frame = Instant::now();
+    }
+}
+
+fn load_autosave() -> Option<SavedGame> {
+    let path = SAVE_FILE.get_default_path()?;
+
+    if !path.exists() {
+        return None;
+    }
+
+    match load_game(&path) {
+        Ok(saved_game) => Some(saved_game),
+        Err(err) => {
+            eprintln!("{err}");
+            None
         }
     }
 }
 
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.viewer.is_some() {
+            self.update_viewer(ctx);
+            ctx.request_repaint();
+            return;
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            if self.game_over {
-                ui.centered_and_justified(|ui| {
-                    ui.heading(format!("Game Over!\nFinal Score: {}", self.game.score))
-                });
+            if self.saved_game.is_some() {
+                self.paint_resume_prompt(ui);
                 return;
             }
 
-            let events = ui.input().events.clone();
+            if self.is_choosing_mode {
+                self.paint_mode_menu(ui);
+                return;
+            }
 
-            for event in &events {
-                self.set_pause_or_unpause(event);
+            if let Some(end_of_game) = self.session.game_over {
+                self.paint_game_over(ui, end_of_game);
+                return;
+            }
+
+            let time_now = Instant::now();
+            let frame_time = time_now.duration_since(self.last_frame);
+            self.last_frame = time_now;
 
-                self.set_move_command(event);
+            let events = ui.input().events.clone();
 
-                self.set_rotation_command(event);
+            for event in &events {
+                if let egui::Event::Key { key, pressed, .. } = event {
+                    self.session.handle_key(key.name(), *pressed);
+                }
             }
 
-            paint_rectangle(ui);
+            self.paint_rectangle(ui);
 
-            if self.is_paused {
+            if self.session.is_paused {
                 ui.centered_and_justified(|ui| ui.heading("PAUSED"));
                 return;
             } else {
-                let score = self.game.score;
-                ui.heading(format!("SCORE: {}", score));
+                self.paint_hud(ui);
             }
 
             self.paint_pieces(ui);
+            self.paint_side_panel(ui);
             self.paint_projection(ui);
 
-            let time_now = Instant::now();
-            let delta_t = time_now.duration_since(self.time);
-            let fine_grained_delta_t = time_now.duration_since(self.fine_grained_time);
+            for err in self.session.advance(frame_time) {
+                eprintln!("{err}");
+            }
+        });
 
-            if fine_grained_delta_t >= Duration::from_millis((10. * (1. / FPS)) as u64) {
-                self.process_move_command(time_now);
+        ctx.request_repaint();
+    }
+
+    /// Saves the game in progress for the next launch, or clears the save once the game
+    /// is over. A save the player has not answered the prompt for is left alone.
+    fn on_close_event(&mut self) -> bool {
+        let Some(path) = SAVE_FILE.get_default_path() else {
+            return true;
+        };
+
+        if self.saved_game.is_some() || self.viewer.is_some() {
+            return true;
+        }
+
+        let result = if self.session.game_over.is_some() {
+            std::fs::remove_file(&path).or_else(|err| match err.kind() {
+                std::io::ErrorKind::NotFound => Ok(()),
+                _ => Err(err.into()),
+            })
+        } else if self.is_choosing_mode {
+            // The game on screen has not started, any earlier save is kept for next time.
+            Ok(())
+        } else {
+            save_game(&path, &self.session.game, self.session.is_paused)
+        };
+
+        if let Err(err) = result {
+            eprintln!("{err}");
+        }
+
+        true
+    }
+}
+
+impl MyApp {
+    fn update_viewer(&mut self, ctx: &egui::Context) {
+        let time_now = Instant::now();
+        let frame_time = time_now.duration_since(self.last_frame);
+        self.last_frame = time_now;
+
+        if let Some(viewer) = &mut self.viewer {
+            for event in &ctx.input().events {
+                viewer.handle_key(event);
+            }
+
+            viewer.advance(frame_time);
+
+            egui::TopBottomPanel::bottom("replay_controls")
+                .show(ctx, |ui| viewer.paint_controls(ui));
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.paint_rectangle(ui);
+            self.paint_hud(ui);
+
+            if let Some(end_of_game) = self
+                .viewer
+                .as_ref()
+                .and_then(|viewer| viewer.player.get_end_of_game())
+            {
+                ui.label(format!("GAME OVER: {end_of_game}"));
             }
 
-            if delta_t >= Duration::from_millis((2500. * (1. / FPS)) as u64) {
-                let game_still_on = self.game.step(StepKind::GoDown);
+            self.paint_pieces(ui);
+            self.paint_side_panel(ui);
+            self.paint_projection(ui);
+        });
+    }
+
+    fn paint_hud(&self, ui: &mut egui::Ui) {
+        let game = self.shown_game();
 
-                match game_still_on {
-                    Ok(()) => self.game_over = false,
-                    Err(EndOfGame) => self.game_over = true,
+        ui.heading(format!("SCORE: {}", game.score));
+        ui.label(format!("SEED: {}", game.get_seed()));
+
+        match game.get_time_left() {
+            Some(time_left) => ui.label(format!("TIME LEFT: {}", format_time(time_left))),
+            None => ui.label(format!("TIME: {}", format_time(game.get_time()))),
+        };
+
+        if let Some(lines_left) = game.get_lines_left() {
+            ui.label(format!("LINES LEFT: {lines_left}"));
+        }
+
+        if game.get_garbage_cleared() > 0 {
+            ui.label(format!("GARBAGE CLEARED: {}", game.get_garbage_cleared()));
+        }
+
+        if let Some(lock_result) = game.get_last_lock_result() {
+            for label in lock_result.get_labels() {
+                ui.label(label);
+            }
+        }
+    }
+
+    /// The final score, and for a sprint its time, its splits and the personal best.
+    fn paint_game_over(&mut self, ui: &mut egui::Ui, end_of_game: EndOfGame) {
+        ui.vertical_centered(|ui| {
+            match end_of_game {
+                EndOfGame::GoalReached => ui.heading("Finished!"),
+                EndOfGame::TimeUp => ui.heading("Time Up!"),
+                _ => ui.heading(format!("Game Over!\n{end_of_game}")),
+            };
+            ui.heading(format!("Final Score: {}", self.session.game.score));
+            ui.label(format!("LEVEL: {}", self.session.game.get_level()));
+            ui.label(format!("LINES: {}", self.session.game.get_lines_cleared()));
+
+            if self.session.game.get_garbage_cleared() > 0 {
+                ui.label(format!(
+                    "GARBAGE CLEARED: {}",
+                    self.session.game.get_garbage_cleared()
+                ));
+            }
+
+            if ui.button("PLAY AGAIN").clicked() {
+                self.start_game(self.session.game.config().mode);
+            }
+
+            if ui.button("MENU").clicked() {
+                self.is_choosing_mode = true;
+            }
+
+            match self.session.game.config().mode {
+                GameMode::Sprint => {}
+                GameMode::Dig { .. } => {
+                    ui.heading(format!(
+                        "Time: {}",
+                        format_time(self.session.game.get_time())
+                    ));
+                    return;
                 }
+                _ => return,
+            }
 
-                self.time = time_now;
-                self.current_move_command = Command::None;
-                self.current_rotation_command = Command::None;
+            ui.heading(format!(
+                "Time: {}",
+                format_time(self.session.game.get_time())
+            ));
+
+            for (index, split) in self.session.game.get_splits().iter().enumerate() {
+                ui.label(format!(
+                    "{} LINES: {}",
+                    (index as u32 + 1) * SPLIT_LINES,
+                    format_time(*split)
+                ));
+            }
+
+            match &self.session.sprint_result {
+                Some(sprint_result) if sprint_result.is_new_best() => {
+                    ui.heading("NEW PERSONAL BEST!");
+                }
+                Some(SprintResult {
+                    previous_best: Some(best),
+                    ..
+                }) => {
+                    ui.label(format!("PERSONAL BEST: {}", format_time(best.time)));
+                }
+                _ => {}
             }
         });
+    }
 
-        ctx.request_repaint();
+    fn paint_mode_menu(&mut self, ui: &mut egui::Ui) {
+        let mut modes = vec![
+            ("ENDLESS".to_string(), GameMode::Endless),
+            (format!("SPRINT {SPRINT_LINES} LINES"), GameMode::Sprint),
+            (
+                format!("MARATHON {MARATHON_LINES} LINES"),
+                GameMode::Marathon { endless: false },
+            ),
+            (
+                "MARATHON ENDLESS".to_string(),
+                GameMode::Marathon { endless: true },
+            ),
+        ];
+
+        for lines in DIG_LINE_GOALS {
+            modes.push((format!("DIG {lines} LINES"), GameMode::Dig { lines }));
+        }
+
+        for seconds in ULTRA_TIME_LIMITS {
+            modes.push((
+                format!("ULTRA {} MINUTES", seconds / 60),
+                GameMode::Ultra(Duration::from_secs(seconds)),
+            ));
+        }
+
+        ui.vertical_centered(|ui| {
+            ui.heading("CHOOSE A MODE");
+
+            for (label, mode) in modes {
+                if ui.button(label).clicked() {
+                    self.start_game(mode);
+                }
+            }
+        });
     }
-}
 
-fn paint_rectangle(ui: &mut egui::Ui) {
-    ui.painter().rect_stroke(
-        Rect {
-            min: Pos2 { x: 0., y: 0. },
-            max: Pos2 {
-                x: GAME_WIDTH as f32,
-                y: HEIGHT as f32,
+    fn paint_resume_prompt(&mut self, ui: &mut egui::Ui) {
+        let Some(saved_game) = &self.saved_game else {
+            return;
+        };
+        let score = saved_game.game.score;
+
+        ui.vertical_centered(|ui| {
+            ui.heading("RESUME SAVED GAME?");
+            ui.label(format!("SCORE: {score}"));
+
+            if ui.button("RESUME").clicked() {
+                if let Some(saved_game) = self.saved_game.take() {
+                    self.resume(saved_game);
+                }
+            }
+
+            if ui.button("NEW GAME").clicked() {
+                self.saved_game = None;
+                self.last_frame = Instant::now();
+            }
+        });
+    }
+
+    fn paint_rectangle(&self, ui: &mut egui::Ui) {
+        let game_width = self.shown_game().config().width as f32 * self.cell_size;
+        let game_height = self.shown_game().config().visible_height as f32 * self.cell_size;
+
+        ui.painter().rect_stroke(
+            Rect {
+                min: Pos2 { x: 0., y: 0. },
+                max: Pos2 {
+                    x: game_width,
+                    y: game_height,
+                },
+            },
+            0.,
+            Stroke {
+                width: 1.,
+                color: Color32::GRAY,
+            },
+        );
+
+        ui.painter().rect_stroke(
+            Rect {
+                min: Pos2 {
+                    x: game_width,
+                    y: 0.,
+                },
+                max: Pos2 {
+                    x: game_width + NEXT_PIECE_DISPLAY_WIDTH as f32,
+                    y: game_height,
+                },
             },
-        },
-        0.,
-        Stroke {
-            width: 1.,
-            color: Color32::GRAY,
-        },
-    );
-
-    ui.painter().rect_stroke(
+            0.,
+            Stroke {
+                width: 1.,
+                color: Color32::GRAY,
+            },
+        );
+    }
+
+    /// Rectangle of a cell on screen, counting rows from the top of the visible playfield.
+    fn cell_rect(&self, col: i32, row: i32) -> Rect {
         Rect {
             min: Pos2 {
-                x: GAME_WIDTH as f32,
-                y: 0.,
+                x: col as f32 * self.cell_size,
+                y: row as f32 * self.cell_size,
             },
             max: Pos2 {
-                x: (GAME_WIDTH + NEXT_PIECE_DISPLAY_WIDTH) as f32,
-                y: HEIGHT as f32,
+                x: (col + 1) as f32 * self.cell_size,
+                y: (row + 1) as f32 * self.cell_size,
             },
-        },
-        0.,
-        Stroke {
-            width: 1.,
-            color: Color32::GRAY,
-        },
-    );
-}
+        }
+    }
 
-impl MyApp {
-    fn get_command(&self, pressed: &bool, key: &egui::Key) -> Command {
-        if *pressed {
-            match key {
-                egui::Key::ArrowUp => Command::Movement(Movement::UP),
-                egui::Key::ArrowDown => Command::Movement(Movement::DOWN),
-                egui::Key::ArrowLeft => Command::Movement(Movement::LEFT),
-                egui::Key::ArrowRight => Command::Movement(Movement::RIGHT),
-                egui::Key::E => Command::Rotation(Rotation::CW),
-                egui::Key::Q => Command::Rotation(Rotation::CCW),
-                egui::Key::Z => Command::DropDown,
-                _ => self.current_move_command,
+    /// Rectangle of a board cell, or `None` if it lies in the hidden buffer.
+    fn board_cell_rect(&self, coord: [i32; 2]) -> Option<Rect> {
+        let row = coord[1] - self.shown_game().config().buffer_height;
+
+        (row >= 0).then(|| self.cell_rect(coord[0], row))
+    }
+
+    fn paint_pieces(&self, ui: &mut egui::Ui) {
+        let rounding = self.cell_size / 5.;
+
+        for square in self.shown_game().list_squares() {
+            if let Some(rect) = self.board_cell_rect(square.0) {
+                ui.painter().rect_filled(rect, rounding, square.1)
             }
-        } else {
-            self.current_move_command
         }
     }
 
-    fn paint_pieces(&self, ui: &mut egui::Ui) {
-        for square in self.game.list_squares() {
-            ui.painter().rect_filled(
-                Rect {
-                    min: Pos2 {
-                        x: square.0[0] as f32 - SCALE as f32 / 2.,
-                        y: square.0[1] as f32 - SCALE as f32 / 2.,
-                    },
-                    max: Pos2 {
-                        x: square.0[0] as f32 + SCALE as f32 / 2.,
-                        y: square.0[1] as f32 + SCALE as f32 / 2.,
-                    },
-                },
-                SCALE as f32 / 5.,
-                square.1,
-            )
+    /// Stacks the next queue, the hold slot and the level and line counters in the side
+    /// panel, shrinking the previews when they would not fit.
+    fn paint_side_panel(&self, ui: &mut egui::Ui) {
+        let game = self.shown_game();
+        let panel_height = game.config().visible_height as f32 * self.cell_size;
+
+        let next_pieces: Vec<Piece> = game
+            .get_next_pieces(self.options.preview_count)
+            .into_iter()
+            .map(|shape| get_piece_display(shape, game.config().rotation_system.get()))
+            .collect();
+
+        let queue_rows: i32 = next_pieces
+            .iter()
+            .map(|piece| piece.get_size()[1] + 1)
+            .sum();
+        // Two labels, the queue, the hold slot and a gap under it, then the two counters.
+        let rows = 2 + queue_rows + HOLD_ROWS + 1 + 2;
+        let preview_cell_size = self.cell_size.min(panel_height / rows as f32);
+
+        let mut top = self.paint_label(ui, "NEXT", 0., preview_cell_size);
+
+        for piece in next_pieces.iter() {
+            self.paint_preview(ui, piece, piece.color, top, preview_cell_size);
+            top += (piece.get_size()[1] + 1) as f32 * preview_cell_size;
+        }
+
+        top = self.paint_label(ui, "HOLD", top, preview_cell_size);
+
+        if let Some(held_shape) = game.get_held_piece() {
+            let held_piece = get_piece_display(held_shape, game.config().rotation_system.get());
+
+            let color = if game.can_hold() {
+                held_piece.color
+            } else {
+                held_piece.color.linear_multiply(0.3)
+            };
+
+            self.paint_preview(ui, &held_piece, color, top, preview_cell_size);
         }
 
-        let next_piece = self.game.get_next_piece();
+        top += (HOLD_ROWS + 1) as f32 * preview_cell_size;
+        top = self.paint_label(
+            ui,
+            &format!("LEVEL {}", game.get_level()),
+            top,
+            preview_cell_size,
+        );
+        self.paint_label(
+            ui,
+            &format!("LINES {}", game.get_lines_cleared()),
+            top,
+            preview_cell_size,
+        );
+    }
+
+    /// Paints a label centered in the side panel and returns the top of the next row.
+    fn paint_label(&self, ui: &mut egui::Ui, label: &str, top: f32, cell_size: f32) -> f32 {
+        ui.painter().text(
+            Pos2 {
+                x: self.panel_left() + NEXT_PIECE_DISPLAY_WIDTH as f32 / 2.,
+                y: top,
+            },
+            Align2::CENTER_TOP,
+            label,
+            FontId::proportional(cell_size * 0.8),
+            Color32::GRAY,
+        );
+
+        top + cell_size
+    }
+
+    fn paint_preview(
+        &self,
+        ui: &mut egui::Ui,
+        piece: &Piece,
+        color: Color32,
+        top: f32,
+        cell_size: f32,
+    ) {
+        let left = self.panel_left()
+            + (NEXT_PIECE_DISPLAY_WIDTH as f32 - piece.get_size()[0] as f32 * cell_size) / 2.;
+
+        for coord in piece.coords.iter() {
+            let min = Pos2 {
+                x: left + coord[0] as f32 * cell_size,
+                y: top + coord[1] as f32 * cell_size,
+            };
 
-        for coord in next_piece.coords {
             ui.painter().rect_filled(
                 Rect {
-                    min: Pos2 {
-                        x: coord[0] as f32 - SCALE as f32 / 2.,
-                        y: coord[1] as f32 - SCALE as f32 / 2.,
-                    },
-                    max: Pos2 {
-                        x: coord[0] as f32 + SCALE as f32 / 2.,
-                        y: coord[1] as f32 + SCALE as f32 / 2.,
-                    },
+                    min,
+                    max: min + egui::vec2(cell_size, cell_size),
                 },
-                SCALE as f32 / 5.,
-                next_piece.color,
+                cell_size / 5.,
+                color,
             )
         }
     }
 
+    fn panel_left(&self) -> f32 {
+        self.shown_game().config().width as f32 * self.cell_size
+    }
+
     fn paint_projection(&self, ui: &mut egui::Ui) {
-        let mut phantom_piece = self.game.player_piece.clone();
-
-        loop {
-            let mut next_phantom_piece = phantom_piece.clone();
-            next_phantom_piece.step_down();
-
-            if next_phantom_piece.hits_bottom()
-                || next_phantom_piece.intersect(
-                    &self
-                        .game
-                        .frozen_squares
-                        .iter()
-                        .map(|colored_point| colored_point.0)
-                        .collect(),
+        let Some(projection) = self.shown_game().get_projection() else {
+            return;
+        };
+
+        for square in projection.coords {
+            if let Some(rect) = self.board_cell_rect(square) {
+                ui.painter().rect_stroke(
+                    rect,
+                    self.cell_size / 5.,
+                    Stroke {
+                        width: 1.,
+                        color: projection.color,
+                    },
                 )
-            {
-                break;
-            } else {
-                phantom_piece = next_phantom_piece;
             }
         }
+    }
+}
 
-        for square in phantom_piece.coords {
-            ui.painter().rect_stroke(
-                Rect {
-                    min: Pos2 {
-                        x: square[0] as f32 - SCALE as f32 / 2.,
-                        y: square[1] as f32 - SCALE as f32 / 2.,
-                    },
-                    max: Pos2 {
-                        x: square[0] as f32 + SCALE as f32 / 2.,
-                        y: square[1] as f32 + SCALE as f32 / 2.,
-                    },
-                },
-                SCALE as f32 / 5.,
-                Stroke {
-                    width: 1.,
-                    color: phantom_piece.color,
-                },
-            )
+/// Playback state of the replay viewer.
+struct ReplayViewer {
+    player: ReplayPlayer,
+    /// Replay time on screen, which can fall between two frames.
+    time: Duration,
+    speed: f32,
+    is_playing: bool,
+}
+
+impl ReplayViewer {
+    fn new(replay: Replay) -> Self {
+        ReplayViewer {
+            player: ReplayPlayer::new(replay),
+            time: Duration::ZERO,
+            speed: 1.,
+            is_playing: true,
         }
     }
 
-    fn set_pause_or_unpause(&mut self, event: &egui::Event) {
-        self.is_paused = match event {
-            egui::Event::Key {
-                key: egui::Key::Space,
-                pressed,
-                modifiers: _,
-            } => {
-                if *pressed {
-                    !self.is_paused
-                } else {
-                    self.is_paused
-                }
-            }
-            _ => self.is_paused,
+    fn advance(&mut self, frame_time: Duration) {
+        if !self.is_playing {
+            return;
         }
-    }
 
-    fn set_move_command(&mut self, event: &egui::Event) {
-        self.current_move_command = match event {
-            egui::Event::Key {
-                key,
-                pressed,
-                modifiers: _,
-            } => match self.get_command(pressed, key) {
-                Command::Movement(x) => Command::Movement(x),
-                Command::DropDown => Command::DropDown,
-                _ => self.current_move_command,
-            },
-            _ => self.current_move_command,
-        };
+        self.time += frame_time.mul_f32(self.speed);
+        self.player.seek_time(self.time);
+
+        if self.time >= self.player.replay().get_duration() {
+            self.is_playing = false;
+        }
     }
 
-    fn set_rotation_command(&mut self, event: &egui::Event) {
-        self.current_rotation_command = match event {
-            egui::Event::Key {
-                key,
-                pressed,
-                modifiers: _,
-            } => {
-                if let Command::Rotation(x) = self.get_command(pressed, key) {
-                    Command::Rotation(x)
-                } else {
-                    self.current_rotation_command
-                }
-            }
-            _ => self.current_rotation_command,
+    fn toggle_playing(&mut self) {
+        if !self.is_playing && self.player.is_finished() {
+            self.seek_time(Duration::ZERO);
         }
+
+        self.is_playing = !self.is_playing;
     }
 
-    fn process_move_command(&mut self, time_now: Instant) {
-        match self.current_move_command {
-            Command::Movement(movement) => self.game.step(StepKind::Move(Some(movement))).unwrap(),
-            Command::DropDown => self.game.step(StepKind::HardDrop).unwrap(),
-            _ => (),
+    fn seek_time(&mut self, time: Duration) {
+        self.time = time;
+        self.player.seek_time(time);
+    }
+
+    /// Runs a seek on the player while paused, keeping the clock on the frame it lands on.
+    fn seek_paused(&mut self, seek: impl FnOnce(&mut ReplayPlayer)) {
+        self.is_playing = false;
+        seek(&mut self.player);
+        self.time = self.player.get_time();
+    }
+
+    fn step_forward(&mut self) {
+        self.seek_paused(|player| {
+            player.step_forward();
+        });
+    }
+
+    fn step_backward(&mut self) {
+        self.seek_paused(|player| player.seek(player.get_frame().saturating_sub(1)));
+    }
+
+    /// Space plays and pauses, left and right step through frames and up and down through
+    /// placements.
+    fn handle_key(&mut self, event: &egui::Event) {
+        let egui::Event::Key {
+            key, pressed: true, ..
+        } = event
+        else {
+            return;
+        };
+
+        match key {
+            egui::Key::Space => self.toggle_playing(),
+            egui::Key::ArrowLeft => self.step_backward(),
+            egui::Key::ArrowRight => self.step_forward(),
+            egui::Key::ArrowUp => self.seek_paused(ReplayPlayer::seek_previous_lock),
+            egui::Key::ArrowDown => self.seek_paused(ReplayPlayer::seek_next_lock),
+            _ => {}
         }
-        if let Command::Rotation(rotation) = self.current_rotation_command {
-            self.game.step(StepKind::Rotate(rotation)).unwrap()
+    }
+
+    fn paint_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("|<").clicked() {
+                self.seek_paused(ReplayPlayer::seek_previous_lock);
+            }
+
+            if ui.button("<").clicked() {
+                self.step_backward();
+            }
+
+            if ui
+                .button(if self.is_playing { "PAUSE" } else { "PLAY" })
+                .clicked()
+            {
+                self.toggle_playing();
+            }
+
+            if ui.button(">").clicked() {
+                self.step_forward();
+            }
+
+            if ui.button(">|").clicked() {
+                self.seek_paused(ReplayPlayer::seek_next_lock);
+            }
+
+            for speed in REPLAY_SPEEDS {
+                ui.selectable_value(&mut self.speed, speed, format!("{speed}x"));
+            }
+        });
+
+        let duration = self.player.replay().get_duration().as_secs_f32();
+        let mut seconds = self.time.as_secs_f32().min(duration);
+
+        ui.spacing_mut().slider_width = ui.available_width() - 80.;
+
+        if ui
+            .add(egui::Slider::new(&mut seconds, 0.0..=duration).suffix(" s"))
+            .changed()
+        {
+            self.seek_time(Duration::from_secs_f32(seconds));
         }
-        self.fine_grained_time = time_now;
-        self.current_move_command = Command::None;
-        self.current_rotation_command = Command::None;
     }
 }
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::game::{EndOfGame, Game};
+
+/// Lines to clear in a sprint.
+pub const SPRINT_LINES: u32 = 40;
+
+/// Levels in a marathon, which stays at the last one once reached.
+pub const MARATHON_LEVELS: u32 = 15;
+
+/// Lines to clear in a marathon, ten for each level.
+pub const MARATHON_LINES: u32 = 150;
+
+/// Garbage lines to clear in the digs offered.
+pub const DIG_LINE_GOALS: [u32; 2] = [10, 100];
+
+/// Most garbage rows on the board at once in a dig, refilled as they are cleared.
+pub const DIG_ROWS: u32 = 10;
+
+/// Time limits offered for Ultra, in seconds.
+pub const ULTRA_TIME_LIMITS: [u64; 2] = [120, 180];
+
+/// Lines between two splits of the game timer.
+pub const SPLIT_LINES: u32 = 10;
+
+/// What the player is playing for. Whatever the mode, topping out still ends the game.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Plays on until the stack tops out.
+    #[default]
+    Endless,
+    /// Clears `SPRINT_LINES` lines as fast as possible.
+    Sprint,
+    /// Scores as much as possible before the time limit.
+    Ultra(Duration),
+    /// Clears `MARATHON_LINES` lines through `MARATHON_LEVELS` levels, or keeps going at the
+    /// last level in the endless variant.
+    Marathon { endless: bool },
+    /// Clears `lines` lines of garbage, pushed up from the bottom of the board, as fast as
+    /// possible.
+    Dig { lines: u32 },
+}
+
+impl GameMode {
+    /// Lines that win the game, if any.
+    pub fn get_line_goal(&self) -> Option<u32> {
+        match self {
+            GameMode::Sprint => Some(SPRINT_LINES),
+            GameMode::Marathon { endless: false } => Some(MARATHON_LINES),
+            GameMode::Dig { lines } => Some(*lines),
+            _ => None,
+        }
+    }
+
+    /// Level the game stops speeding up at, if any.
+    pub fn get_max_level(&self) -> Option<u32> {
+        match self {
+            GameMode::Marathon { .. } => Some(MARATHON_LEVELS),
+            _ => None,
+        }
+    }
+
+    /// Game time after which the game ends, if any.
+    pub fn get_time_limit(&self) -> Option<Duration> {
+        match self {
+            GameMode::Ultra(time_limit) => Some(*time_limit),
+            _ => None,
+        }
+    }
+
+    /// How `game` ends by the rules of the mode, if it is over. The game asks after every
+    /// lock and every frame, so a new mode only has to say when it is won or lost here.
+    pub fn get_end_of_game(&self, game: &Game) -> Option<EndOfGame> {
+        if game.get_lines_left() == Some(0) {
+            return Some(EndOfGame::GoalReached);
+        }
+
+        if game
+            .get_time_left()
+            .is_some_and(|time_left| time_left.is_zero())
+        {
+            return Some(EndOfGame::TimeUp);
+        }
+
+        None
+    }
+}
+
+/// Parses `endless`, `sprint`, `marathon`, `marathon-endless`, `ultra` followed by an
+/// optional time limit in minutes, two by default, such as `ultra3`, or `dig` followed by
+/// an optional number of lines, ten by default, such as `dig100`.
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "endless" => Ok(GameMode::Endless),
+            "sprint" | "40l" => Ok(GameMode::Sprint),
+            "marathon" => Ok(GameMode::Marathon { endless: false }),
+            "marathon-endless" => Ok(GameMode::Marathon { endless: true }),
+            "ultra" => Ok(GameMode::Ultra(Duration::from_secs(ULTRA_TIME_LIMITS[0]))),
+            "dig" => Ok(GameMode::Dig {
+                lines: DIG_LINE_GOALS[0],
+            }),
+            mode => {
+                if let Some(Ok(minutes)) = mode.strip_prefix("ultra").map(str::parse::<u64>) {
+                    if minutes > 0 {
+                        return Ok(GameMode::Ultra(Duration::from_secs(60 * minutes)));
+                    }
+                }
+
+                if let Some(Ok(lines)) = mode.strip_prefix("dig").map(str::parse::<u32>) {
+                    if lines > 0 {
+                        return Ok(GameMode::Dig { lines });
+                    }
+                }
+
+                Err(format!("Unknown game mode: {s}"))
+            }
+        }
+    }
+}
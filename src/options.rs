@@ -0,0 +1,96 @@
+use std::fs;
+use std::time::Duration;
+
+use crate::game::{new_game, new_game_with_seed, Game, GameConfig};
+use crate::gravity::MAX_STARTING_LEVEL;
+
+impl GameConfig {
+    /// Applies a command line flag such as `--width 12` or `--rotation ars`, so every
+    /// frontend accepts the same rules.
+    pub fn apply_flag(&mut self, flag: &str, value: &str) -> Result<(), String> {
+        let number = value.parse::<i32>().ok();
+
+        match (flag, number) {
+            ("--width", Some(number)) => self.width = number.max(4),
+            ("--height", Some(number)) => self.visible_height = number.max(4),
+            ("--buffer", Some(number)) => self.buffer_height = number.max(0),
+            ("--level", Some(number)) => {
+                self.starting_level = number.clamp(1, MAX_STARTING_LEVEL as i32) as u32
+            }
+            ("--entry-delay", Some(number)) => {
+                self.entry_delay = Duration::from_millis(number.max(0) as u64)
+            }
+            ("--das", Some(number)) => {
+                self.handling.das = Duration::from_millis(number.max(0) as u64)
+            }
+            ("--arr", Some(number)) => {
+                self.handling.arr = Duration::from_millis(number.max(0) as u64)
+            }
+            ("--sdf", _) => match value.parse::<f32>() {
+                Ok(factor) if factor >= 1. => self.handling.soft_drop_factor = factor,
+                _ => return Err(format!("Ignoring invalid argument: {flag}")),
+            },
+            ("--gravity", _) => self.speed_curve = value.parse()?,
+            ("--gravity-file", _) => match fs::read_to_string(value) {
+                Ok(table) => self.speed_curve = table.parse()?,
+                Err(err) => return Err(format!("Could not read {value}: {err}")),
+            },
+            ("--rotation", _) => self.rotation_system = value.parse()?,
+            ("--randomizer", _) => self.randomizer = value.parse()?,
+            ("--mode", _) => self.mode = value.parse()?,
+            ("--messiness", _) => match value.parse::<f32>() {
+                Ok(messiness) if (0. ..=1.).contains(&messiness) => {
+                    self.garbage_messiness = messiness
+                }
+                _ => return Err(format!("Ignoring invalid argument: {flag}")),
+            },
+            _ => return Err(format!("Ignoring invalid argument: {flag}")),
+        }
+
+        Ok(())
+    }
+}
+
+/// What both frontends take from the command line: the rules, and how to start games.
+pub struct LaunchOptions {
+    pub config: GameConfig,
+    /// Pieces shown in the queue, from 1 to 7.
+    pub preview_count: usize,
+    /// Seed every new game starts from, if one was given.
+    pub seed: Option<u64>,
+}
+
+impl LaunchOptions {
+    pub fn new(preview_count: usize) -> LaunchOptions {
+        LaunchOptions {
+            config: GameConfig::default(),
+            preview_count,
+            seed: None,
+        }
+    }
+
+    /// Applies `--previews`, `--seed` or any flag `GameConfig::apply_flag` takes.
+    pub fn apply_flag(&mut self, flag: &str, value: &str) -> Result<(), String> {
+        match flag {
+            "--previews" => match value.parse::<i32>() {
+                Ok(number) => self.preview_count = number.clamp(1, 7) as usize,
+                Err(_) => return Err(format!("Ignoring invalid argument: {flag}")),
+            },
+            "--seed" => match value.parse() {
+                Ok(number) => self.seed = Some(number),
+                Err(_) => return Err(format!("Invalid seed: {value}")),
+            },
+            _ => self.config.apply_flag(flag, value)?,
+        }
+
+        Ok(())
+    }
+
+    /// A new game under `config`, from the seed if one was given.
+    pub fn start_game(&self, config: GameConfig) -> Game {
+        match self.seed {
+            Some(seed) => new_game_with_seed(config, seed),
+            None => new_game(config),
+        }
+    }
+}
@@ -1,16 +1,18 @@
 use rand::{
     distributions::{Distribution, Standard},
-    seq::SliceRandom,
-    thread_rng, Rng,
+    Rng,
 };
-use std::collections::HashSet;
 
-use crate::constants::{Movement, GAME_WIDTH, HEIGHT, SCALE};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{Movement, Rotation};
+use crate::game::GameConfig;
+use crate::rotation::RotationSystem;
 use egui::Color32;
 
 pub const NUMBER_OF_SHAPES: u8 = 7;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum PieceShape {
     L,
     I,
@@ -23,372 +25,125 @@ pub enum PieceShape {
 
 impl Distribution<PieceShape> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> PieceShape {
-        match rng.gen_range(0..=6) {
-            0 => PieceShape::L,
-            1 => PieceShape::I,
-            2 => PieceShape::Z,
-            3 => PieceShape::Square,
-            4 => PieceShape::T,
-            5 => PieceShape::InvertedZ,
-            6 => PieceShape::InvertedL,
-            _ => panic!(),
-        }
+        PieceShape::ALL[rng.gen_range(0..PieceShape::ALL.len())]
     }
 }
 
 impl PieceShape {
-    pub fn get_kicks(&self) -> Vec<[i32; 2]> {
+    pub const ALL: [PieceShape; NUMBER_OF_SHAPES as usize] = [
+        PieceShape::L,
+        PieceShape::I,
+        PieceShape::Z,
+        PieceShape::Square,
+        PieceShape::T,
+        PieceShape::InvertedZ,
+        PieceShape::InvertedL,
+    ];
+
+    pub fn get_color(&self) -> Color32 {
         match self {
-            PieceShape::L => vec![
-                [0, 0],
-                [SCALE, 0],
-                [-SCALE, 0],
-                [0, -SCALE],
-                [-SCALE, -SCALE],
-                [SCALE, -SCALE],
-            ],
-            PieceShape::I => vec![
-                [0, 0],
-                [SCALE, 0],
-                [-SCALE, 0],
-                [2 * SCALE, 0],
-                [-2 * SCALE, 0],
-                [0, -SCALE],
-                [-SCALE, -SCALE],
-                [SCALE, -SCALE],
-                [0, -SCALE],
-                [-SCALE, -2 * SCALE],
-                [SCALE, -2 * SCALE],
-            ],
-            PieceShape::Z => vec![
-                [0, 0],
-                [SCALE, 0],
-                [-SCALE, 0],
-                [0, -SCALE],
-                [-SCALE, -SCALE],
-                [SCALE, -SCALE],
-            ],
-            PieceShape::Square => vec![[0, 0]],
-            PieceShape::T => vec![
-                [0, 0],
-                [SCALE, 0],
-                [-SCALE, 0],
-                [0, -SCALE],
-                [-SCALE, -SCALE],
-                [SCALE, -SCALE],
-            ],
-            PieceShape::InvertedZ => vec![
-                [0, 0],
-                [SCALE, 0],
-                [-SCALE, 0],
-                [0, -SCALE],
-                [-SCALE, -SCALE],
-                [SCALE, -SCALE],
-            ],
-            PieceShape::InvertedL => vec![
-                [0, 0],
-                [SCALE, 0],
-                [-SCALE, 0],
-                [0, -SCALE],
-                [-SCALE, -SCALE],
-                [SCALE, -SCALE],
-            ],
+            PieceShape::L => Color32::from_rgb(255, 165, 0),
+            PieceShape::InvertedL => Color32::WHITE,
+            PieceShape::I => Color32::from_rgb(173, 216, 230),
+            PieceShape::Z => Color32::GREEN,
+            PieceShape::Square => Color32::from_rgb(255, 255, 102),
+            PieceShape::T => Color32::from_rgb(255, 20, 147),
+            PieceShape::InvertedZ => Color32::RED,
         }
     }
 
-    fn from(i: usize) -> Option<PieceShape> {
-        match i {
-            0 => Some(PieceShape::L),
-            1 => Some(PieceShape::I),
-            2 => Some(PieceShape::Z),
-            3 => Some(PieceShape::Square),
-            4 => Some(PieceShape::T),
-            5 => Some(PieceShape::InvertedZ),
-            6 => Some(PieceShape::InvertedL),
-            _ => None,
+    /// Side of the square box that rotation tables place the piece in.
+    pub fn get_box_size(&self) -> i32 {
+        match self {
+            PieceShape::I | PieceShape::Square => 4,
+            _ => 3,
         }
     }
+}
 
-    pub fn generate_fair_collection(n: usize) -> Vec<PieceShape> {
-        let mut possibilities = (0..=6).cycle().take(n * 7).collect::<Vec<usize>>();
-        possibilities.shuffle(&mut thread_rng());
+/// A piece in its spawn orientation, moved so its bounding box starts at `[0, 0]`.
+pub fn get_piece_display(piece_shape: PieceShape, rotation_system: &dyn RotationSystem) -> Piece {
+    let piece = rotation_system.spawn(piece_shape, &GameConfig::default());
 
-        possibilities
-            .into_iter()
-            .map(|x| PieceShape::from(x).unwrap())
-            .collect()
-    }
+    let min_col = piece.coords.iter().map(|coord| coord[0]).min().unwrap_or(0);
+    let min_row = piece.coords.iter().map(|coord| coord[1]).min().unwrap_or(0);
+
+    piece.kick([-min_col, -min_row])
 }
 
-pub fn spawn_piece_above(piece_shape: PieceShape) -> Piece {
-    match piece_shape {
-        PieceShape::L => Piece {
-            coords: vec![
-                [GAME_WIDTH / 2 - SCALE / 2, 0],
-                [GAME_WIDTH / 2 - SCALE / 2, SCALE],
-                [GAME_WIDTH / 2 - SCALE / 2, 2 * SCALE],
-                [GAME_WIDTH / 2 + SCALE / 2, 2 * SCALE],
-            ],
-            center: [GAME_WIDTH / 2 - SCALE / 2, SCALE],
-            color: Color32::from_rgb(255, 165, 0),
-            piece_shape: PieceShape::L,
-        },
-        PieceShape::InvertedL => Piece {
-            coords: vec![
-                [GAME_WIDTH / 2 - SCALE / 2, 0],
-                [GAME_WIDTH / 2 - SCALE / 2, SCALE],
-                [GAME_WIDTH / 2 - SCALE / 2, 2 * SCALE],
-                [GAME_WIDTH / 2 - SCALE / 2 - SCALE, 2 * SCALE],
-            ],
-            center: [GAME_WIDTH / 2 - SCALE / 2, SCALE],
-            color: Color32::WHITE,
-            piece_shape: PieceShape::InvertedL,
-        },
-        PieceShape::I => Piece {
-            coords: vec![
-                [GAME_WIDTH / 2 - SCALE / 2, 0],
-                [GAME_WIDTH / 2 - SCALE / 2, SCALE],
-                [GAME_WIDTH / 2 - SCALE / 2, 2 * SCALE],
-                [GAME_WIDTH / 2 - SCALE / 2, 3 * SCALE],
-            ],
-            center: [GAME_WIDTH / 2 - SCALE / 2, 2 * SCALE],
-            color: Color32::from_rgb(173, 216, 230),
-            piece_shape: PieceShape::I,
-        },
-        PieceShape::Z => Piece {
-            coords: vec![
-                [GAME_WIDTH / 2 - SCALE / 2, 0],
-                [GAME_WIDTH / 2 - SCALE / 2, SCALE],
-                [GAME_WIDTH / 2 - SCALE / 2 + SCALE, SCALE],
-                [GAME_WIDTH / 2 - SCALE / 2 + SCALE, 2 * SCALE],
-            ],
-            center: [GAME_WIDTH / 2 - SCALE / 2, SCALE],
-            color: Color32::GREEN,
-            piece_shape: PieceShape::Z,
-        },
-        PieceShape::Square => Piece {
-            coords: vec![
-                [GAME_WIDTH / 2 - SCALE / 2, 0],
-                [GAME_WIDTH / 2 - SCALE / 2, SCALE],
-                [GAME_WIDTH / 2 - SCALE / 2 + SCALE, SCALE],
-                [GAME_WIDTH / 2 - SCALE / 2 + SCALE, 0],
-            ],
-            center: [GAME_WIDTH / 2, SCALE / 2],
-            color: Color32::from_rgb(255, 255, 102),
-            piece_shape: PieceShape::Square,
-        },
-        PieceShape::T => Piece {
-            coords: vec![
-                [GAME_WIDTH / 2 - SCALE / 2 - SCALE, 0],
-                [GAME_WIDTH / 2 - SCALE / 2, 0],
-                [GAME_WIDTH / 2 - SCALE / 2 + SCALE, 0],
-                [GAME_WIDTH / 2 - SCALE / 2, SCALE],
-            ],
-            center: [GAME_WIDTH / 2 - SCALE / 2, 0],
-            color: Color32::from_rgb(255, 20, 147),
-            piece_shape: PieceShape::T,
-        },
-        PieceShape::InvertedZ => Piece {
-            coords: vec![
-                [GAME_WIDTH / 2 - SCALE / 2 + SCALE, 0],
-                [GAME_WIDTH / 2 - SCALE / 2 + SCALE, SCALE],
-                [GAME_WIDTH / 2 - SCALE / 2, SCALE],
-                [GAME_WIDTH / 2 - SCALE / 2, 2 * SCALE],
-            ],
-            center: [GAME_WIDTH / 2 - SCALE / 2, SCALE],
-            color: Color32::RED,
-            piece_shape: PieceShape::InvertedZ,
-        },
-    }
+/// Orientation of a piece, named after the SRS states.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum RotationState {
+    Spawn,
+    Right,
+    Two,
+    Left,
 }
 
-pub fn get_next_piece_display(piece_shape: PieceShape) -> Piece {
-    const REF_Y: i32 = 80;
-    const REF_X: i32 = 100;
+impl RotationState {
+    /// Number of clockwise quarter turns away from the spawn state.
+    pub fn get_turns(&self) -> usize {
+        match self {
+            RotationState::Spawn => 0,
+            RotationState::Right => 1,
+            RotationState::Two => 2,
+            RotationState::Left => 3,
+        }
+    }
 
-    match piece_shape {
-        PieceShape::L => Piece {
-            coords: vec![
-                [GAME_WIDTH + REF_X, REF_Y],
-                [GAME_WIDTH + REF_X, REF_Y + SCALE],
-                [GAME_WIDTH + REF_X, REF_Y + 2 * SCALE],
-                [GAME_WIDTH + REF_X + SCALE, REF_Y + 2 * SCALE],
-            ],
-            center: [GAME_WIDTH / 2, SCALE],
-            color: Color32::from_rgb(255, 165, 0),
-            piece_shape: PieceShape::L,
-        },
-        PieceShape::InvertedL => Piece {
-            coords: vec![
-                [GAME_WIDTH + REF_X + SCALE, REF_Y],
-                [GAME_WIDTH + REF_X + SCALE, REF_Y + SCALE],
-                [GAME_WIDTH + REF_X + SCALE, REF_Y + 2 * SCALE],
-                [GAME_WIDTH + REF_X + SCALE - SCALE, REF_Y + 2 * SCALE],
-            ],
-            center: [GAME_WIDTH / 2, SCALE],
-            color: Color32::WHITE,
-            piece_shape: PieceShape::InvertedL,
-        },
-        PieceShape::I => Piece {
-            coords: vec![
-                [GAME_WIDTH + REF_X, REF_Y],
-                [GAME_WIDTH + REF_X, REF_Y + SCALE],
-                [GAME_WIDTH + REF_X, REF_Y + 2 * SCALE],
-                [GAME_WIDTH + REF_X, REF_Y + 3 * SCALE],
-            ],
-            center: [GAME_WIDTH / 2, 2 * SCALE],
-            color: Color32::from_rgb(173, 216, 230),
-            piece_shape: PieceShape::I,
-        },
-        PieceShape::Z => Piece {
-            coords: vec![
-                [GAME_WIDTH + REF_X, REF_Y],
-                [GAME_WIDTH + REF_X, REF_Y + SCALE],
-                [GAME_WIDTH + REF_X + SCALE, REF_Y + SCALE],
-                [GAME_WIDTH + REF_X + SCALE, REF_Y + 2 * SCALE],
-            ],
-            center: [GAME_WIDTH / 2, SCALE],
-            color: Color32::GREEN,
-            piece_shape: PieceShape::Z,
-        },
-        PieceShape::Square => Piece {
-            coords: vec![
-                [GAME_WIDTH + REF_X, REF_Y],
-                [GAME_WIDTH + REF_X, REF_Y + SCALE],
-                [GAME_WIDTH + REF_X + SCALE, REF_Y + SCALE],
-                [GAME_WIDTH + REF_X + SCALE, REF_Y],
-            ],
-            center: [GAME_WIDTH / 2 + SCALE / 2, SCALE / 2],
-            color: Color32::from_rgb(255, 255, 102),
-            piece_shape: PieceShape::Square,
-        },
-        PieceShape::T => Piece {
-            coords: vec![
-                [GAME_WIDTH + REF_X + SCALE, REF_Y],
-                [GAME_WIDTH + REF_X + SCALE, REF_Y + SCALE],
-                [GAME_WIDTH + REF_X + SCALE, REF_Y + 2 * SCALE],
-                [GAME_WIDTH + REF_X, REF_Y + SCALE],
-            ],
-            center: [GAME_WIDTH / 2, 0],
-            color: Color32::from_rgb(255, 20, 147),
-            piece_shape: PieceShape::T,
-        },
-        PieceShape::InvertedZ => Piece {
-            coords: vec![
-                [GAME_WIDTH + REF_X + SCALE, REF_Y],
-                [GAME_WIDTH + REF_X + SCALE, REF_Y + SCALE],
-                [GAME_WIDTH + REF_X, REF_Y + SCALE],
-                [GAME_WIDTH + REF_X, REF_Y + 2 * SCALE],
-            ],
-            center: [GAME_WIDTH / 2, SCALE],
-            color: Color32::RED,
-            piece_shape: PieceShape::InvertedZ,
-        },
+    pub fn rotate(self, rotation: Rotation) -> RotationState {
+        use RotationState::*;
+
+        match (self, rotation) {
+            (Spawn, Rotation::CW) | (Two, Rotation::CCW) => Right,
+            (Right, Rotation::CW) | (Left, Rotation::CCW) => Two,
+            (Two, Rotation::CW) | (Spawn, Rotation::CCW) => Left,
+            (Left, Rotation::CW) | (Right, Rotation::CCW) => Spawn,
+        }
     }
 }
 
-#[derive(Clone)]
+/// A tetromino in board coordinates: `[column, row]`, with row 0 at the top.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Piece {
     pub coords: Vec<[i32; 2]>,
-    pub center: [i32; 2],
+    /// Top left corner of the box the rotation system lays the piece out in.
+    pub origin: [i32; 2],
     pub color: Color32,
     pub piece_shape: PieceShape,
+    pub rotation_state: RotationState,
 }
 
 impl Piece {
-    pub fn rotate_ccw(&mut self) {
-        let translated_coords: Vec<[i32; 2]> = self
-            .coords
-            .iter()
-            .map(|tup| [tup[0] - self.center[0], tup[1] - self.center[1]])
-            .collect();
-
-        self.coords = translated_coords
-            .iter()
-            .map(|tup| [-tup[1] + self.center[0], tup[0] + self.center[1]])
-            .collect();
+    /// Width and height of the bounding box, in cells.
+    pub fn get_size(&self) -> [i32; 2] {
+        let cols = self.coords.iter().map(|coord| coord[0]);
+        let rows = self.coords.iter().map(|coord| coord[1]);
+
+        [
+            cols.clone().max().unwrap_or(0) - cols.min().unwrap_or(0) + 1,
+            rows.clone().max().unwrap_or(0) - rows.min().unwrap_or(0) + 1,
+        ]
     }
 
-    pub fn rotate_cw(&mut self) {
-        let translated_coords: Vec<[i32; 2]> = self
-            .coords
-            .iter()
-            .map(|tup| [tup[0] - self.center[0], tup[1] - self.center[1]])
-            .collect();
-
-        self.coords = translated_coords
-            .iter()
-            .map(|tup| [tup[1] + self.center[0], -tup[0] + self.center[1]])
-            .collect();
+    /// Lowest row the piece occupies.
+    pub fn get_bottom(&self) -> i32 {
+        self.coords.iter().map(|coord| coord[1]).max().unwrap_or(0)
     }
 
     pub fn step_down(&mut self) {
-        self.coords = self
-            .coords
-            .iter()
-            .map(|tup| [tup[0], tup[1] + SCALE])
-            .collect();
-        self.center = [self.center[0], self.center[1] + SCALE]
+        *self = self.kick([0, 1]);
     }
 
     pub fn make_move(&mut self, movement: Option<Movement>) {
-        self.coords = match movement {
-            Some(x) => match x {
-                Movement::UP => self.coords.clone(),
-                Movement::DOWN => self
-                    .coords
-                    .iter()
-                    .map(|tup| [tup[0], tup[1] + SCALE])
-                    .collect(),
-                Movement::RIGHT => self
-                    .coords
-                    .iter()
-                    .map(|tup| [tup[0] + SCALE, tup[1]])
-                    .collect(),
-                Movement::LEFT => self
-                    .coords
-                    .iter()
-                    .map(|tup| [tup[0] - SCALE, tup[1]])
-                    .collect(),
-            },
-            None => self.coords.clone(),
-        };
-
-        self.center = match movement {
-            Some(x) => match x {
-                Movement::UP => self.center,
-                Movement::DOWN => [self.center[0], self.center[1] + SCALE],
-                Movement::RIGHT => [self.center[0] + SCALE, self.center[1]],
-                Movement::LEFT => [self.center[0] - SCALE, self.center[1]],
-            },
-            None => self.center,
+        let vector = match movement {
+            Some(Movement::DOWN) => [0, 1],
+            Some(Movement::RIGHT) => [1, 0],
+            Some(Movement::LEFT) => [-1, 0],
+            Some(Movement::UP) | None => [0, 0],
         };
-    }
-
-    pub fn intersect(&self, points: &Vec<[i32; 2]>) -> bool {
-        let my_pieces: HashSet<&[i32; 2]> = HashSet::from_iter(self.coords.iter());
-        let points_set: HashSet<&[i32; 2]> = HashSet::from_iter(points.iter());
-
-        my_pieces.intersection(&points_set).count() > 0
-    }
-
-    pub fn hits_bottom(&self) -> bool {
-        for coord in self.coords.clone() {
-            if coord[1] >= HEIGHT {
-                return true;
-            }
-        }
-
-        false
-    }
-    pub fn hits_sides(&self) -> bool {
-        for coord in self.coords.clone() {
-            if coord[0] >= GAME_WIDTH || coord[0] <= 0 {
-                return true;
-            }
-        }
 
-        false
+        *self = self.kick(vector);
     }
 
     pub fn kick(&self, vector: [i32; 2]) -> Piece {
@@ -398,12 +153,13 @@ impl Piece {
             .map(|arr| [arr[0] + vector[0], arr[1] + vector[1]])
             .collect();
 
-        let new_center = [self.center[0] + vector[0], self.center[1] + vector[1]];
+        let new_origin = [self.origin[0] + vector[0], self.origin[1] + vector[1]];
         Piece {
             coords: new_coords,
-            center: new_center,
+            origin: new_origin,
             color: self.color,
             piece_shape: self.piece_shape,
+            rotation_state: self.rotation_state,
         }
     }
 }
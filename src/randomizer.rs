@@ -0,0 +1,265 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_pcg::Pcg64;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use crate::pieces::PieceShape;
+
+/// Decides which shape comes next. Randomness comes from the game's seeded generator, so
+/// a randomizer only has to keep its own history.
+pub trait Randomizer {
+    fn next_shape(&mut self, rng: &mut Pcg64) -> PieceShape;
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum RandomizerKind {
+    #[default]
+    SevenBag,
+    FourteenBag,
+    Tgm,
+    Nes,
+    Uniform,
+}
+
+impl RandomizerKind {
+    pub fn build(&self) -> AnyRandomizer {
+        match self {
+            RandomizerKind::SevenBag => AnyRandomizer::Bag(Bag::new(1)),
+            RandomizerKind::FourteenBag => AnyRandomizer::Bag(Bag::new(2)),
+            RandomizerKind::Tgm => AnyRandomizer::Tgm(TgmHistory::new(4)),
+            RandomizerKind::Nes => AnyRandomizer::Nes(NesReroll::default()),
+            RandomizerKind::Uniform => AnyRandomizer::Uniform(Uniform),
+        }
+    }
+}
+
+/// One of the randomizers above along with its state, so it can be saved with the game.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum AnyRandomizer {
+    Bag(Bag),
+    Tgm(TgmHistory),
+    Nes(NesReroll),
+    Uniform(Uniform),
+}
+
+impl Randomizer for AnyRandomizer {
+    fn next_shape(&mut self, rng: &mut Pcg64) -> PieceShape {
+        match self {
+            AnyRandomizer::Bag(randomizer) => randomizer.next_shape(rng),
+            AnyRandomizer::Tgm(randomizer) => randomizer.next_shape(rng),
+            AnyRandomizer::Nes(randomizer) => randomizer.next_shape(rng),
+            AnyRandomizer::Uniform(randomizer) => randomizer.next_shape(rng),
+        }
+    }
+}
+
+impl FromStr for RandomizerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "7bag" | "bag" => Ok(RandomizerKind::SevenBag),
+            "14bag" => Ok(RandomizerKind::FourteenBag),
+            "tgm" => Ok(RandomizerKind::Tgm),
+            "nes" => Ok(RandomizerKind::Nes),
+            "uniform" | "random" => Ok(RandomizerKind::Uniform),
+            _ => Err(format!("Unknown randomizer: {s}")),
+        }
+    }
+}
+
+/// Deals every shape `copies` times in a shuffled order before refilling.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bag {
+    copies: usize,
+    bag: Vec<PieceShape>,
+}
+
+impl Bag {
+    pub fn new(copies: usize) -> Bag {
+        Bag {
+            copies,
+            bag: Vec::new(),
+        }
+    }
+}
+
+impl Randomizer for Bag {
+    fn next_shape(&mut self, rng: &mut Pcg64) -> PieceShape {
+        if self.bag.is_empty() {
+            self.bag = PieceShape::ALL.repeat(self.copies);
+            self.bag.shuffle(rng);
+        }
+
+        self.bag.pop().unwrap()
+    }
+}
+
+/// TGM's randomizer: rolls up to `rolls` times for a shape that is not among the last
+/// four dealt. The history starts full of Z, and the first piece is never S, Z or O.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TgmHistory {
+    rolls: u32,
+    history: VecDeque<PieceShape>,
+    is_first: bool,
+}
+
+impl TgmHistory {
+    pub fn new(rolls: u32) -> TgmHistory {
+        TgmHistory {
+            rolls,
+            history: VecDeque::from([PieceShape::InvertedZ; 4]),
+            is_first: true,
+        }
+    }
+}
+
+impl Randomizer for TgmHistory {
+    fn next_shape(&mut self, rng: &mut Pcg64) -> PieceShape {
+        let shape = if self.is_first {
+            self.is_first = false;
+
+            *[
+                PieceShape::I,
+                PieceShape::T,
+                PieceShape::L,
+                PieceShape::InvertedL,
+            ]
+            .choose(rng)
+            .unwrap()
+        } else {
+            let mut shape: PieceShape = rng.gen();
+
+            for _ in 1..self.rolls {
+                if !self.history.contains(&shape) {
+                    break;
+                }
+
+                shape = rng.gen();
+            }
+
+            shape
+        };
+
+        self.history.pop_front();
+        self.history.push_back(shape);
+
+        shape
+    }
+}
+
+/// The NES randomizer: rolls one of eight outcomes, and rolls again among the seven
+/// shapes on the dummy eighth outcome or a repeat of the previous shape.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct NesReroll {
+    last: Option<PieceShape>,
+}
+
+impl Randomizer for NesReroll {
+    fn next_shape(&mut self, rng: &mut Pcg64) -> PieceShape {
+        let roll = rng.gen_range(0..=PieceShape::ALL.len());
+
+        let shape = match PieceShape::ALL.get(roll) {
+            Some(shape) if Some(*shape) != self.last => *shape,
+            _ => rng.gen(),
+        };
+
+        self.last = Some(shape);
+
+        shape
+    }
+}
+
+/// Every shape equally likely, every time.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Uniform;
+
+impl Randomizer for Uniform {
+    fn next_shape(&mut self, rng: &mut Pcg64) -> PieceShape {
+        rng.gen()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn deal(kind: RandomizerKind, seed: u64, count: usize) -> Vec<PieceShape> {
+        let mut rng = Pcg64::seed_from_u64(seed);
+        let mut randomizer = kind.build();
+
+        (0..count)
+            .map(|_| randomizer.next_shape(&mut rng))
+            .collect()
+    }
+
+    fn count(shapes: &[PieceShape], shape: PieceShape) -> usize {
+        shapes.iter().filter(|other| **other == shape).count()
+    }
+
+    #[test]
+    fn bags_deal_every_shape_before_refilling() {
+        for (kind, copies) in [
+            (RandomizerKind::SevenBag, 1),
+            (RandomizerKind::FourteenBag, 2),
+        ] {
+            for bag in deal(kind, 3, 7 * copies * 20).chunks(7 * copies) {
+                for shape in PieceShape::ALL {
+                    assert_eq!(count(bag, shape), copies);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tgm_never_starts_with_s_z_or_o() {
+        for seed in 0..200 {
+            let first = deal(RandomizerKind::Tgm, seed, 1)[0];
+
+            assert!(!matches!(
+                first,
+                PieceShape::Z | PieceShape::InvertedZ | PieceShape::Square
+            ));
+        }
+    }
+
+    #[test]
+    fn tgm_rarely_deals_a_shape_from_its_history() {
+        let shapes = deal(RandomizerKind::Tgm, 1, 7000);
+        let repeats = shapes
+            .windows(5)
+            .filter(|window| window[..4].contains(&window[4]))
+            .count();
+
+        // Without rerolls, about half of the shapes would be among the last four.
+        assert!(repeats < shapes.len() / 5, "{repeats} repeats");
+    }
+
+    #[test]
+    fn nes_rerolls_repeats() {
+        let shapes = deal(RandomizerKind::Nes, 1, 7000);
+        let repeats = shapes.windows(2).filter(|pair| pair[0] == pair[1]).count();
+
+        // A uniform dealer repeats one shape in seven; the reroll leaves about one in 28.
+        assert!(repeats < shapes.len() / 14, "{repeats} repeats");
+
+        for shape in PieceShape::ALL {
+            assert!(count(&shapes, shape) > 700);
+        }
+    }
+
+    #[test]
+    fn the_same_seed_deals_the_same_shapes() {
+        for kind in [
+            RandomizerKind::SevenBag,
+            RandomizerKind::Tgm,
+            RandomizerKind::Nes,
+            RandomizerKind::Uniform,
+        ] {
+            assert_eq!(deal(kind, 5, 100), deal(kind, 5, 100));
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::game::{EndOfGame, Game, GameConfig};
+use crate::mode::GameMode;
+use crate::save::{write_json, DataFile, SaveError};
+
+/// Personal bests, next to the save file.
+pub const RECORDS_FILE: DataFile = DataFile {
+    name: "records.json",
+    version: 2,
+};
+
+/// A finished sprint.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SprintRecord {
+    pub time: Duration,
+    /// Time at which every `SPLIT_LINES` lines were reached.
+    pub splits: Vec<Duration>,
+    pub seed: u64,
+    /// Rules the sprint was played under. Only sprints with the same rules are compared.
+    pub config: GameConfig,
+}
+
+impl SprintRecord {
+    /// The record of `game` if it is a sprint that reached its goal.
+    pub fn from_game(game: &Game, end_of_game: EndOfGame) -> Option<SprintRecord> {
+        let is_finished_sprint =
+            game.config().mode == GameMode::Sprint && end_of_game == EndOfGame::GoalReached;
+
+        is_finished_sprint.then(|| SprintRecord {
+            time: game.get_time(),
+            splits: game.get_splits(),
+            seed: game.get_seed(),
+            config: game.config().clone(),
+        })
+    }
+}
+
+/// Personal bests, kept across sessions.
+#[derive(Serialize, Deserialize)]
+pub struct Records {
+    pub version: u32,
+    /// The best sprint for every set of rules sprints were finished under.
+    pub sprints: Vec<SprintRecord>,
+}
+
+impl Default for Records {
+    fn default() -> Self {
+        Records {
+            version: RECORDS_FILE.version,
+            sprints: Vec::new(),
+        }
+    }
+}
+
+impl Records {
+    /// The best sprint played under the rules of `config`.
+    pub fn get_best_sprint(&self, config: &GameConfig) -> Option<&SprintRecord> {
+        self.sprints
+            .iter()
+            .find(|best| best.config.has_same_rules(config))
+    }
+
+    /// Keeps `record` if it beats the personal best for its rules, returning whether it did.
+    pub fn submit_sprint(&mut self, record: SprintRecord) -> bool {
+        let best = self
+            .sprints
+            .iter_mut()
+            .find(|best| best.config.has_same_rules(&record.config));
+
+        match best {
+            Some(best) if record.time < best.time => *best = record,
+            Some(_) => return false,
+            None => self.sprints.push(record),
+        }
+
+        true
+    }
+}
+
+/// A finished sprint next to the personal best it was up against.
+pub struct SprintResult {
+    pub record: SprintRecord,
+    pub previous_best: Option<SprintRecord>,
+}
+
+impl SprintResult {
+    pub fn is_new_best(&self) -> bool {
+        match &self.previous_best {
+            Some(best) => self.record.time < best.time,
+            None => true,
+        }
+    }
+}
+
+pub fn save_records(path: &Path, records: &Records) -> Result<(), SaveError> {
+    write_json(path, records)
+}
+
+/// Reads the records at `path`, or returns empty ones if nothing has been recorded yet.
+pub fn load_records(path: &Path) -> Result<Records, SaveError> {
+    if !path.exists() {
+        return Ok(Records::default());
+    }
+
+    RECORDS_FILE.read(path)
+}
+
+/// Adds a finished sprint to the records at `path`, keeping it if it is a personal best.
+pub fn record_sprint(path: &Path, record: SprintRecord) -> Result<SprintResult, SaveError> {
+    let mut records = load_records(path)?;
+    let previous_best = records.get_best_sprint(&record.config).cloned();
+
+    if records.submit_sprint(record.clone()) {
+        save_records(path, &records)?;
+    }
+
+    Ok(SprintResult {
+        record,
+        previous_best,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sprint(seconds: u64, config: GameConfig) -> SprintRecord {
+        SprintRecord {
+            time: Duration::from_secs(seconds),
+            splits: Vec::new(),
+            seed: 0,
+            config,
+        }
+    }
+
+    #[test]
+    fn personal_bests_are_kept_for_each_set_of_rules() {
+        let standard = GameConfig {
+            mode: GameMode::Sprint,
+            ..GameConfig::default()
+        };
+        let narrow = GameConfig {
+            width: 4,
+            ..standard.clone()
+        };
+        let mut records = Records::default();
+
+        assert!(records.submit_sprint(sprint(60, standard.clone())));
+        assert!(records.submit_sprint(sprint(30, narrow.clone())));
+        assert!(!records.submit_sprint(sprint(70, standard.clone())));
+
+        let mut fast_handling = standard.clone();
+        fast_handling.handling.arr = Duration::ZERO;
+
+        assert!(records.submit_sprint(sprint(50, fast_handling)));
+        assert_eq!(records.sprints.len(), 2);
+        assert_eq!(
+            records.get_best_sprint(&standard).unwrap().time,
+            Duration::from_secs(50)
+        );
+        assert_eq!(
+            records.get_best_sprint(&narrow).unwrap().time,
+            Duration::from_secs(30)
+        );
+    }
+}
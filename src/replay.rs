@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::clock::{to_duration, FRAME_TIME};
+use crate::game::{new_game_with_seed, EndOfGame, Game, GameConfig, GameEvent};
+use crate::input::Input;
+use crate::save::{write_json, DataFile, SaveError};
+
+/// Frames between two keyframes of a `ReplayPlayer`, ten seconds of play.
+const KEYFRAME_INTERVAL: u64 = 600;
+
+/// A directory with a replay for every finished game.
+pub const REPLAY_FILES: DataFile = DataFile {
+    name: "replays",
+    version: 6,
+};
+
+/// An input and the frame it was given on. Gravity, auto shift and the lock delay need no
+/// inputs, they follow from the number of frames.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct ReplayInput {
+    pub frame: u64,
+    pub input: Input,
+}
+
+/// The seed, the rules and every input of a game, which is all it takes to play it again.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub config: GameConfig,
+    pub inputs: Vec<ReplayInput>,
+    /// Frames the game lasted.
+    pub frame_count: u64,
+}
+
+impl Replay {
+    pub fn new(seed: u64, config: GameConfig) -> Replay {
+        Replay {
+            version: REPLAY_FILES.version,
+            seed,
+            config,
+            inputs: Vec::new(),
+            frame_count: 0,
+        }
+    }
+
+    pub fn record(&mut self, frame: u64, input: Input) {
+        self.inputs.push(ReplayInput { frame, input });
+    }
+
+    pub fn get_duration(&self) -> Duration {
+        to_duration(self.frame_count)
+    }
+
+    /// Plays the whole replay, returning the game as it ended along with how it ended.
+    pub fn play(&self) -> (Game, Option<EndOfGame>) {
+        let mut player = ReplayPlayer::new(self.clone());
+        player.seek(self.frame_count);
+
+        let end_of_game = player.get_end_of_game();
+
+        (player.game, end_of_game)
+    }
+}
+
+/// The game as it was on a frame, along with the first input it had not played yet.
+struct Keyframe {
+    game: Game,
+    next_input: usize,
+}
+
+/// Plays a replay back one frame at a time. Going backwards starts over from the last
+/// keyframe before the frame sought, so only the frames after it are played again.
+pub struct ReplayPlayer {
+    replay: Replay,
+    game: Game,
+    /// Index of the first input not played yet.
+    next_input: usize,
+    end_of_game: Option<EndOfGame>,
+    /// Frames right after each lock played so far.
+    lock_frames: Vec<u64>,
+    /// A copy of the game every `KEYFRAME_INTERVAL` frames played so far, oldest first.
+    keyframes: Vec<Keyframe>,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> ReplayPlayer {
+        let game = new_game_with_seed(replay.config.clone(), replay.seed);
+
+        ReplayPlayer {
+            keyframes: vec![Keyframe {
+                game: game.clone(),
+                next_input: 0,
+            }],
+            game,
+            replay,
+            next_input: 0,
+            end_of_game: None,
+            lock_frames: Vec::new(),
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Number of frames played so far.
+    pub fn get_frame(&self) -> u64 {
+        self.game.get_frame()
+    }
+
+    pub fn get_time(&self) -> Duration {
+        self.game.get_time()
+    }
+
+    pub fn get_end_of_game(&self) -> Option<EndOfGame> {
+        self.end_of_game
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.get_frame() >= self.replay.frame_count || self.end_of_game.is_some()
+    }
+
+    /// Plays the next frame with its inputs, if any frame is left.
+    pub fn step_forward(&mut self) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+
+        let frame = self.get_frame();
+        let first_input = self.next_input;
+
+        while self
+            .replay
+            .inputs
+            .get(self.next_input)
+            .is_some_and(|input| input.frame <= frame)
+        {
+            self.next_input += 1;
+        }
+
+        let inputs: Vec<_> = self.replay.inputs[first_input..self.next_input]
+            .iter()
+            .map(|input| input.input)
+            .collect();
+
+        self.end_of_game = self.game.tick(&inputs).err();
+
+        if self
+            .game
+            .drain_events()
+            .iter()
+            .any(|event| matches!(event, GameEvent::Locked(_)))
+        {
+            self.lock_frames.push(self.get_frame());
+        }
+
+        let frame = self.get_frame();
+        let is_new_keyframe = self
+            .keyframes
+            .last()
+            .is_some_and(|keyframe| keyframe.game.get_frame() < frame);
+
+        if self.end_of_game.is_none() && frame.is_multiple_of(KEYFRAME_INTERVAL) && is_new_keyframe
+        {
+            self.keyframes.push(Keyframe {
+                game: self.game.clone(),
+                next_input: self.next_input,
+            });
+        }
+
+        true
+    }
+
+    /// Brings the game to the state it was in after the first `frame` frames.
+    pub fn seek(&mut self, frame: u64) {
+        if frame < self.get_frame() {
+            let keyframe = self
+                .keyframes
+                .iter()
+                .rev()
+                .find(|keyframe| keyframe.game.get_frame() <= frame)
+                .expect("the first keyframe is on frame 0");
+            let keyframe_frame = keyframe.game.get_frame();
+
+            self.game = keyframe.game.clone();
+            self.next_input = keyframe.next_input;
+            self.end_of_game = None;
+            self.lock_frames
+                .retain(|lock_frame| *lock_frame <= keyframe_frame);
+        }
+
+        while self.get_frame() < frame && self.step_forward() {}
+    }
+
+    /// Plays up to and including the next lock, one placement forward.
+    pub fn seek_next_lock(&mut self) {
+        let locks = self.lock_frames.len();
+
+        while self.lock_frames.len() == locks && self.step_forward() {}
+    }
+
+    /// Goes back to right after the lock before the current frame, one placement back.
+    pub fn seek_previous_lock(&mut self) {
+        let frame = self
+            .lock_frames
+            .iter()
+            .rev()
+            .find(|lock_frame| **lock_frame < self.get_frame())
+            .copied()
+            .unwrap_or(0);
+
+        self.seek(frame);
+    }
+
+    /// Brings the game to the state it was in at `time`.
+    pub fn seek_time(&mut self, time: Duration) {
+        self.seek((time.as_nanos() / FRAME_TIME.as_nanos()) as u64);
+    }
+}
+
+/// A fresh path in the default replay directory, named after the time and the seed.
+pub fn get_new_replay_path(replay: &Replay) -> Option<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    REPLAY_FILES
+        .get_default_path()
+        .map(|dir| dir.join(format!("{}-{}.json", timestamp, replay.seed)))
+}
+
+pub fn save_replay(path: &Path, replay: &Replay) -> Result<(), SaveError> {
+    write_json(path, replay)
+}
+
+pub fn load_replay(path: &Path) -> Result<Replay, SaveError> {
+    REPLAY_FILES.read(path)
+}
@@ -0,0 +1,362 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::constants::Rotation;
+use crate::game::{Board, GameConfig};
+use crate::pieces::{Piece, PieceShape, RotationState};
+
+/// How pieces spawn, turn and get pushed out of the way when a rotation is blocked.
+pub trait RotationSystem {
+    /// Cells of a shape in the given state, relative to the top left of its box.
+    fn get_cells(&self, piece_shape: PieceShape, rotation_state: RotationState) -> Vec<[i32; 2]>;
+
+    /// Offsets to try, in order, for `rotated`, the result of turning `piece` in place.
+    fn get_kicks(&self, piece: &Piece, rotated: &Piece, board: &Board) -> Vec<[i32; 2]>;
+
+    /// Spawns a piece centered (rounding left) with its bottom at the spawn row.
+    fn spawn(&self, piece_shape: PieceShape, config: &GameConfig) -> Piece {
+        let cells = self.get_cells(piece_shape, RotationState::Spawn);
+        let bottom = cells.iter().map(|cell| cell[1]).max().unwrap_or(0);
+
+        let piece = Piece {
+            coords: cells,
+            origin: [0, 0],
+            color: piece_shape.get_color(),
+            piece_shape,
+            rotation_state: RotationState::Spawn,
+        };
+
+        piece.kick([
+            (config.width - piece_shape.get_box_size()) / 2,
+            config.spawn_row() - bottom,
+        ])
+    }
+
+    /// Turns the piece in place, without checking for collisions.
+    fn rotate(&self, piece: &Piece, rotation: Rotation) -> Piece {
+        let rotation_state = piece.rotation_state.rotate(rotation);
+
+        Piece {
+            coords: self
+                .get_cells(piece.piece_shape, rotation_state)
+                .iter()
+                .map(|cell| [cell[0] + piece.origin[0], cell[1] + piece.origin[1]])
+                .collect(),
+            rotation_state,
+            ..piece.clone()
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum RotationSystemKind {
+    #[default]
+    Srs,
+    Ars,
+    Classic,
+}
+
+impl RotationSystemKind {
+    pub fn get(&self) -> &'static dyn RotationSystem {
+        match self {
+            RotationSystemKind::Srs => &Srs,
+            RotationSystemKind::Ars => &Ars,
+            RotationSystemKind::Classic => &Classic,
+        }
+    }
+}
+
+impl FromStr for RotationSystemKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "srs" => Ok(RotationSystemKind::Srs),
+            "ars" => Ok(RotationSystemKind::Ars),
+            "classic" | "nes" => Ok(RotationSystemKind::Classic),
+            _ => Err(format!("Unknown rotation system: {s}")),
+        }
+    }
+}
+
+/// Turns cells of a `size` wide box clockwise `turns` times about the center of the box.
+fn rotate_cells(cells: &[[i32; 2]], size: i32, turns: usize) -> Vec<[i32; 2]> {
+    let mut cells = cells.to_vec();
+
+    for _ in 0..turns {
+        cells = cells
+            .iter()
+            .map(|cell| [size - 1 - cell[1], cell[0]])
+            .collect();
+    }
+
+    cells
+}
+
+/// The guideline Super Rotation System: true rotation and five kicks per transition.
+pub struct Srs;
+
+impl RotationSystem for Srs {
+    fn get_cells(&self, piece_shape: PieceShape, rotation_state: RotationState) -> Vec<[i32; 2]> {
+        let spawn_cells = match piece_shape {
+            PieceShape::Square => return vec![[1, 0], [2, 0], [1, 1], [2, 1]],
+            PieceShape::L => [[2, 0], [0, 1], [1, 1], [2, 1]],
+            PieceShape::InvertedL => [[0, 0], [0, 1], [1, 1], [2, 1]],
+            PieceShape::I => [[0, 1], [1, 1], [2, 1], [3, 1]],
+            PieceShape::Z => [[1, 0], [2, 0], [0, 1], [1, 1]],
+            PieceShape::T => [[1, 0], [0, 1], [1, 1], [2, 1]],
+            PieceShape::InvertedZ => [[0, 0], [1, 0], [1, 1], [2, 1]],
+        };
+
+        rotate_cells(
+            &spawn_cells,
+            piece_shape.get_box_size(),
+            rotation_state.get_turns(),
+        )
+    }
+
+    /// The offsets are written as in the guideline tables, with y pointing up, and
+    /// flipped to board rows on the way out.
+    fn get_kicks(&self, piece: &Piece, rotated: &Piece, _board: &Board) -> Vec<[i32; 2]> {
+        use RotationState::*;
+
+        let kicks: [[i32; 2]; 5] = match piece.piece_shape {
+            PieceShape::Square => return vec![[0, 0]],
+            PieceShape::I => match (piece.rotation_state, rotated.rotation_state) {
+                (Spawn, Right) => [[0, 0], [-2, 0], [1, 0], [-2, -1], [1, 2]],
+                (Right, Spawn) => [[0, 0], [2, 0], [-1, 0], [2, 1], [-1, -2]],
+                (Right, Two) => [[0, 0], [-1, 0], [2, 0], [-1, 2], [2, -1]],
+                (Two, Right) => [[0, 0], [1, 0], [-2, 0], [1, -2], [-2, 1]],
+                (Two, Left) => [[0, 0], [2, 0], [-1, 0], [2, 1], [-1, -2]],
+                (Left, Two) => [[0, 0], [-2, 0], [1, 0], [-2, -1], [1, 2]],
+                (Left, Spawn) => [[0, 0], [1, 0], [-2, 0], [1, -2], [-2, 1]],
+                (Spawn, Left) => [[0, 0], [-1, 0], [2, 0], [-1, 2], [2, -1]],
+                _ => return vec![[0, 0]],
+            },
+            _ => match (piece.rotation_state, rotated.rotation_state) {
+                (Spawn, Right) | (Two, Right) => [[0, 0], [-1, 0], [-1, 1], [0, -2], [-1, -2]],
+                (Right, Spawn) | (Right, Two) => [[0, 0], [1, 0], [1, -1], [0, 2], [1, 2]],
+                (Two, Left) | (Spawn, Left) => [[0, 0], [1, 0], [1, 1], [0, -2], [1, -2]],
+                (Left, Two) | (Left, Spawn) => [[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]],
+                _ => return vec![[0, 0]],
+            },
+        };
+
+        kicks.iter().map(|kick| [kick[0], -kick[1]]).collect()
+    }
+}
+
+/// Arika's rotation system from the TGM series: pieces rest on the bottom of their box,
+/// spawn flat side up and kick one cell right, then left.
+pub struct Ars;
+
+impl RotationSystem for Ars {
+    fn get_cells(&self, piece_shape: PieceShape, rotation_state: RotationState) -> Vec<[i32; 2]> {
+        let turns = rotation_state.get_turns();
+
+        let spawn_cells = match piece_shape {
+            PieceShape::Square => return vec![[1, 0], [2, 0], [1, 1], [2, 1]],
+            PieceShape::I if turns % 2 == 1 => return vec![[2, 0], [2, 1], [2, 2], [2, 3]],
+            PieceShape::I => return vec![[0, 1], [1, 1], [2, 1], [3, 1]],
+            PieceShape::Z if turns % 2 == 1 => return vec![[0, 0], [0, 1], [1, 1], [1, 2]],
+            PieceShape::Z => return vec![[1, 1], [2, 1], [0, 2], [1, 2]],
+            PieceShape::InvertedZ if turns % 2 == 1 => return vec![[2, 0], [1, 1], [2, 1], [1, 2]],
+            PieceShape::InvertedZ => return vec![[0, 1], [1, 1], [1, 2], [2, 2]],
+            PieceShape::L => [[0, 1], [1, 1], [2, 1], [0, 2]],
+            PieceShape::InvertedL => [[0, 1], [1, 1], [2, 1], [2, 2]],
+            PieceShape::T => [[0, 1], [1, 1], [2, 1], [1, 2]],
+        };
+
+        let cells = rotate_cells(&spawn_cells, 3, turns);
+
+        // Upside down, the flat side would float a row above the bottom of the box.
+        if rotation_state == RotationState::Two {
+            cells.iter().map(|cell| [cell[0], cell[1] + 1]).collect()
+        } else {
+            cells
+        }
+    }
+
+    fn get_kicks(&self, _piece: &Piece, rotated: &Piece, board: &Board) -> Vec<[i32; 2]> {
+        match rotated.piece_shape {
+            PieceShape::I | PieceShape::Square => vec![[0, 0]],
+            PieceShape::L | PieceShape::InvertedL | PieceShape::T
+                if is_blocked_in_center_column(rotated, board) =>
+            {
+                vec![[0, 0]]
+            }
+            _ => vec![[0, 0], [1, 0], [-1, 0]],
+        }
+    }
+}
+
+/// ARS's center column rule: scanning the rotated piece row by row from the top left,
+/// if the first blocked cell is in the middle column of the box, the piece does not kick.
+fn is_blocked_in_center_column(rotated: &Piece, board: &Board) -> bool {
+    let mut blocked_cells: Vec<[i32; 2]> = rotated
+        .coords
+        .iter()
+        .filter(|coord| !board.is_free(coord[0], coord[1]))
+        .copied()
+        .collect();
+
+    blocked_cells.sort_by_key(|coord| (coord[1], coord[0]));
+
+    blocked_cells
+        .first()
+        .is_some_and(|coord| coord[0] == rotated.origin[0] + 1)
+}
+
+/// The NES rotation system: pieces turn about a fixed cell, S, Z and I only have two
+/// orientations, and a blocked rotation simply fails.
+pub struct Classic;
+
+impl RotationSystem for Classic {
+    fn get_cells(&self, piece_shape: PieceShape, rotation_state: RotationState) -> Vec<[i32; 2]> {
+        let turns = rotation_state.get_turns();
+
+        let spawn_cells = match piece_shape {
+            PieceShape::Square => return vec![[1, 0], [2, 0], [1, 1], [2, 1]],
+            PieceShape::I if turns % 2 == 1 => return vec![[2, 0], [2, 1], [2, 2], [2, 3]],
+            PieceShape::I => return vec![[0, 2], [1, 2], [2, 2], [3, 2]],
+            PieceShape::Z if turns % 2 == 1 => return vec![[1, 0], [1, 1], [2, 1], [2, 2]],
+            PieceShape::Z => return vec![[1, 1], [2, 1], [0, 2], [1, 2]],
+            PieceShape::InvertedZ if turns % 2 == 1 => return vec![[2, 0], [1, 1], [2, 1], [1, 2]],
+            PieceShape::InvertedZ => return vec![[0, 1], [1, 1], [1, 2], [2, 2]],
+            PieceShape::L => [[0, 1], [1, 1], [2, 1], [0, 2]],
+            PieceShape::InvertedL => [[0, 1], [1, 1], [2, 1], [2, 2]],
+            PieceShape::T => [[0, 1], [1, 1], [2, 1], [1, 2]],
+        };
+
+        rotate_cells(&spawn_cells, 3, turns)
+    }
+
+    fn get_kicks(&self, _piece: &Piece, _rotated: &Piece, _board: &Board) -> Vec<[i32; 2]> {
+        vec![[0, 0]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::Color32;
+
+    const STATES: [RotationState; 4] = [
+        RotationState::Spawn,
+        RotationState::Right,
+        RotationState::Two,
+        RotationState::Left,
+    ];
+
+    fn columns(piece: &Piece) -> Vec<i32> {
+        let mut columns: Vec<_> = piece.coords.iter().map(|coord| coord[0]).collect();
+        columns.sort();
+        columns.dedup();
+        columns
+    }
+
+    fn in_state(piece: &Piece, rotation_state: RotationState) -> Piece {
+        Piece {
+            rotation_state,
+            ..piece.clone()
+        }
+    }
+
+    #[test]
+    fn srs_pieces_spawn_centered_rounding_left() {
+        let config = GameConfig::default();
+        let spawn = |shape| Srs.spawn(shape, &config);
+
+        assert_eq!(columns(&spawn(PieceShape::I)), [3, 4, 5, 6]);
+        assert_eq!(columns(&spawn(PieceShape::Square)), [4, 5]);
+        assert_eq!(columns(&spawn(PieceShape::T)), [3, 4, 5]);
+        assert_eq!(columns(&spawn(PieceShape::L)), [3, 4, 5]);
+
+        for shape in PieceShape::ALL {
+            assert_eq!(spawn(shape).get_bottom(), config.spawn_row());
+        }
+    }
+
+    #[test]
+    fn srs_t_points_up_at_spawn_and_turns_about_its_center() {
+        let cells = |shape, rotation_state| {
+            let mut cells = Srs.get_cells(shape, rotation_state);
+            cells.sort_by_key(|cell| (cell[1], cell[0]));
+            cells
+        };
+
+        assert_eq!(
+            cells(PieceShape::T, RotationState::Spawn),
+            [[1, 0], [0, 1], [1, 1], [2, 1]]
+        );
+        assert_eq!(
+            cells(PieceShape::T, RotationState::Right),
+            [[1, 0], [1, 1], [2, 1], [1, 2]]
+        );
+        assert_eq!(
+            cells(PieceShape::I, RotationState::Right),
+            [[2, 0], [2, 1], [2, 2], [2, 3]]
+        );
+    }
+
+    #[test]
+    fn srs_kicks_follow_the_guideline_tables() {
+        let board = Board::new(10, 40);
+        let t = Srs.spawn(PieceShape::T, &GameConfig::default());
+        let i = Srs.spawn(PieceShape::I, &GameConfig::default());
+
+        assert_eq!(
+            Srs.get_kicks(&t, &in_state(&t, RotationState::Right), &board),
+            [[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]]
+        );
+        assert_eq!(
+            Srs.get_kicks(&i, &in_state(&i, RotationState::Right), &board),
+            [[0, 0], [-2, 0], [1, 0], [-2, 1], [1, -2]]
+        );
+    }
+
+    #[test]
+    fn srs_kicks_undo_each_other_both_ways() {
+        let board = Board::new(10, 40);
+
+        for shape in [PieceShape::T, PieceShape::I] {
+            let piece = Srs.spawn(shape, &GameConfig::default());
+
+            for from in STATES {
+                for rotation in [Rotation::CW, Rotation::CCW] {
+                    let to = from.rotate(rotation);
+                    let (piece, rotated) = (in_state(&piece, from), in_state(&piece, to));
+
+                    let there = Srs.get_kicks(&piece, &rotated, &board);
+                    let back: Vec<_> = Srs
+                        .get_kicks(&rotated, &piece, &board)
+                        .iter()
+                        .map(|kick| [-kick[0], -kick[1]])
+                        .collect();
+
+                    assert_eq!(there, back, "{shape:?} from {from:?} to {to:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ars_does_not_kick_when_blocked_in_the_center_column_first() {
+        let config = GameConfig::default();
+        let piece = Ars.spawn(PieceShape::T, &config);
+        let rotated = Ars.rotate(&piece, Rotation::CW);
+        let origin = rotated.origin;
+
+        let mut center_blocked = Board::new(config.width, config.total_height());
+        center_blocked.set(origin[0] + 1, origin[1], Some(Color32::GRAY));
+
+        let mut side_blocked = Board::new(config.width, config.total_height());
+        side_blocked.set(origin[0], origin[1] + 1, Some(Color32::GRAY));
+
+        assert_eq!(Ars.get_kicks(&piece, &rotated, &center_blocked), [[0, 0]]);
+        assert_eq!(
+            Ars.get_kicks(&piece, &rotated, &side_blocked),
+            [[0, 0], [1, 0], [-1, 0]]
+        );
+    }
+}
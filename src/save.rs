@@ -0,0 +1,169 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::game::{EndOfGame, Game};
+use crate::records::{record_sprint, SprintRecord, SprintResult, RECORDS_FILE};
+use crate::replay::{get_new_replay_path, save_replay};
+
+/// Directory in the user's data directory where the frontends keep their files.
+const DATA_DIR: &str = "tetris_gui";
+
+/// A kind of file the frontends keep in their data directory.
+pub struct DataFile {
+    /// Name of the file, or of the directory for kinds with a file per game.
+    pub name: &'static str,
+    /// Written into every file, and bumped whenever the files stop being readable by the
+    /// previous format.
+    pub version: u32,
+}
+
+impl DataFile {
+    pub fn get_default_path(&self) -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join(DATA_DIR).join(self.name))
+    }
+
+    /// Reads a file of this kind, refusing any version but the current one.
+    pub(crate) fn read<T: DeserializeOwned>(&self, path: &Path) -> Result<T, SaveError> {
+        let contents = fs::read_to_string(path)?;
+
+        let header: SaveHeader = serde_json::from_str(&contents)?;
+
+        if header.version != self.version {
+            return Err(SaveError::UnsupportedVersion(header.version));
+        }
+
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+/// The game in progress, saved when the window closes.
+pub const SAVE_FILE: DataFile = DataFile {
+    name: "save.json",
+    version: 7,
+};
+
+/// A game in progress, as written to disk.
+#[derive(Serialize, Deserialize)]
+pub struct SavedGame {
+    pub version: u32,
+    pub game: Game,
+    pub is_paused: bool,
+}
+
+/// Just enough of a save file to tell which format it is in.
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+pub fn save_game(path: &Path, game: &Game, is_paused: bool) -> Result<(), SaveError> {
+    #[derive(Serialize)]
+    struct SavedGameRef<'a> {
+        version: u32,
+        game: &'a Game,
+        is_paused: bool,
+    }
+
+    write_json(
+        path,
+        &SavedGameRef {
+            version: SAVE_FILE.version,
+            game,
+            is_paused,
+        },
+    )
+}
+
+/// Reads the game saved at `path`. Keys held when it was saved are let go, as none are down
+/// when it resumes.
+pub fn load_game(path: &Path) -> Result<SavedGame, SaveError> {
+    let mut saved_game: SavedGame = SAVE_FILE.read(path)?;
+
+    saved_game.game.release_held_keys();
+
+    Ok(saved_game)
+}
+
+/// What became of a finished game once kept on disk.
+pub struct FinishedGame {
+    /// How the game compares to the personal best, if it is a finished sprint.
+    pub sprint_result: Option<SprintResult>,
+    /// Whatever failed to save, for the frontend to report.
+    pub errors: Vec<SaveError>,
+}
+
+/// Keeps the replay of a game that just ended, and its time if it is a finished sprint.
+pub fn save_finished_game(game: &Game, end_of_game: EndOfGame) -> FinishedGame {
+    let mut finished_game = FinishedGame {
+        sprint_result: None,
+        errors: Vec::new(),
+    };
+
+    if let (Some(record), Some(path)) = (
+        SprintRecord::from_game(game, end_of_game),
+        RECORDS_FILE.get_default_path(),
+    ) {
+        match record_sprint(&path, record) {
+            Ok(sprint_result) => finished_game.sprint_result = Some(sprint_result),
+            Err(err) => finished_game.errors.push(err),
+        }
+    }
+
+    if let Some(path) = get_new_replay_path(game.get_replay()) {
+        if let Err(err) = save_replay(&path, game.get_replay()) {
+            finished_game.errors.push(err);
+        }
+    }
+
+    finished_game
+}
+
+/// Writes `value` as JSON, creating the parent directories if needed.
+pub(crate) fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), SaveError> {
+    let contents = serde_json::to_string(value)?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, contents)?;
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    /// The file was written by a version of the game that used another format.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "Could not access the save file: {err}"),
+            SaveError::Format(err) => write!(f, "Invalid save file: {err}"),
+            SaveError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported save file version: {version}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(err: serde_json::Error) -> Self {
+        SaveError::Format(err)
+    }
+}
@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::Board;
+use crate::pieces::{Piece, PieceShape};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum TSpin {
+    None,
+    Mini,
+    Full,
+}
+
+/// Index of the last SRS kick for a rotation, which always turns a mini T-spin into a full one.
+const LAST_KICK: usize = 4;
+
+/// Applies the 3-corner rule to a T that has just rotated into place. At least three of the
+/// four cells diagonal to its center must be blocked; the spin is full when both corners
+/// on the pointing side are, or when it took the last kick to get there.
+pub fn detect_t_spin(piece: &Piece, board: &Board, kick_index: usize) -> TSpin {
+    if !matches!(piece.piece_shape, PieceShape::T) {
+        return TSpin::None;
+    }
+
+    let is_neighbour = |a: &[i32; 2], b: &[i32; 2]| (a[0] - b[0]).abs() + (a[1] - b[1]).abs() == 1;
+
+    let Some(center) = piece.coords.iter().find(|cell| {
+        piece
+            .coords
+            .iter()
+            .filter(|other| is_neighbour(cell, other))
+            .count()
+            == 3
+    }) else {
+        return TSpin::None;
+    };
+
+    // The nub is the neighbour of the center without a cell opposite to it.
+    let Some(direction) = piece
+        .coords
+        .iter()
+        .map(|cell| [cell[0] - center[0], cell[1] - center[1]])
+        .filter(|offset| offset[0].abs() + offset[1].abs() == 1)
+        .find(|offset| {
+            !piece
+                .coords
+                .contains(&[center[0] - offset[0], center[1] - offset[1]])
+        })
+    else {
+        return TSpin::None;
+    };
+
+    let is_blocked =
+        |offset: [i32; 2]| !board.is_free(center[0] + offset[0], center[1] + offset[1]);
+
+    let corners = [[-1, -1], [1, -1], [-1, 1], [1, 1]];
+    let blocked_corners = corners.iter().filter(|corner| is_blocked(**corner)).count();
+
+    if blocked_corners < 3 {
+        return TSpin::None;
+    }
+
+    let front_corners = [
+        [direction[0] - direction[1], direction[1] - direction[0]],
+        [direction[0] + direction[1], direction[1] + direction[0]],
+    ];
+
+    if front_corners.iter().all(|corner| is_blocked(*corner)) || kick_index == LAST_KICK {
+        TSpin::Full
+    } else {
+        TSpin::Mini
+    }
+}
+
+/// What a single lock was worth, and why.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LockResult {
+    pub lines: u32,
+    pub t_spin: TSpin,
+    /// Whether this was a difficult clear following another one.
+    pub back_to_back: bool,
+    /// Consecutive locks that cleared lines before this one, if it cleared any.
+    pub combo: u32,
+    pub perfect_clear: bool,
+    pub points: i32,
+}
+
+impl LockResult {
+    /// Labels for the HUD, such as "T-SPIN DOUBLE", "B2B" or "COMBO x3".
+    pub fn get_labels(&self) -> Vec<String> {
+        let mut labels = Vec::new();
+
+        let clear = match self.lines {
+            0 => "",
+            1 => "SINGLE",
+            2 => "DOUBLE",
+            3 => "TRIPLE",
+            _ => "TETRIS",
+        };
+
+        let label = match self.t_spin {
+            TSpin::None => clear.to_string(),
+            TSpin::Mini => format!("MINI T-SPIN {clear}"),
+            TSpin::Full => format!("T-SPIN {clear}"),
+        };
+
+        if !label.trim().is_empty() {
+            labels.push(label.trim().to_string());
+        }
+
+        if self.back_to_back {
+            labels.push("B2B".to_string());
+        }
+
+        if self.combo > 0 {
+            labels.push(format!("COMBO x{}", self.combo));
+        }
+
+        if self.perfect_clear {
+            labels.push("PERFECT CLEAR".to_string());
+        }
+
+        labels
+    }
+}
+
+/// Guideline scoring, keeping track of back-to-back chains and combos between locks.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Scoring {
+    back_to_back_ready: bool,
+    combo: i32,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Scoring {
+            back_to_back_ready: false,
+            combo: -1,
+        }
+    }
+}
+
+impl Scoring {
+    pub fn score_lock(
+        &mut self,
+        lines: u32,
+        t_spin: TSpin,
+        perfect_clear: bool,
+        level: u32,
+    ) -> LockResult {
+        let base = match (t_spin, lines) {
+            (TSpin::None, 0) => 0,
+            (TSpin::None, 1) => 100,
+            (TSpin::None, 2) => 300,
+            (TSpin::None, 3) => 500,
+            (TSpin::None, _) => 800,
+            (TSpin::Mini, 0) => 100,
+            (TSpin::Mini, 1) => 200,
+            (TSpin::Mini, _) => 400,
+            (TSpin::Full, 0) => 400,
+            (TSpin::Full, 1) => 800,
+            (TSpin::Full, 2) => 1200,
+            (TSpin::Full, _) => 1600,
+        };
+
+        let is_difficult = lines >= 4 || (lines > 0 && t_spin != TSpin::None);
+        let back_to_back = is_difficult && self.back_to_back_ready;
+
+        // Locks that clear nothing break combos but leave back-to-back chains alone.
+        if lines > 0 {
+            self.back_to_back_ready = is_difficult;
+            self.combo += 1;
+        } else {
+            self.combo = -1;
+        }
+
+        let combo = self.combo.max(0) as u32;
+
+        let mut points = if back_to_back { base * 3 / 2 } else { base };
+        points += 50 * combo as i32;
+
+        if perfect_clear {
+            points += match lines {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ if back_to_back => 3200,
+                _ => 2000,
+            };
+        }
+
+        LockResult {
+            lines,
+            t_spin,
+            back_to_back,
+            combo,
+            perfect_clear,
+            points: points * level.max(1) as i32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameConfig;
+    use crate::rotation::{RotationSystem, Srs};
+    use egui::Color32;
+
+    /// A T pointing up with its center at column 4 of the second row from the bottom, with
+    /// the given cells diagonal to the center blocked.
+    fn t_spin(corners: &[[i32; 2]], kick_index: usize) -> TSpin {
+        let mut board = Board::new(10, 20);
+        let piece = Srs.spawn(PieceShape::T, &GameConfig::default());
+        let piece = piece.kick([3 - piece.origin[0], 17 - piece.origin[1]]);
+
+        for corner in corners {
+            board.set(4 + corner[0], 18 + corner[1], Some(Color32::GRAY));
+        }
+
+        detect_t_spin(&piece, &board, kick_index)
+    }
+
+    #[test]
+    fn t_spins_need_three_corners_and_both_front_ones_to_be_full() {
+        let back = [[-1, 1], [1, 1]];
+
+        assert_eq!(t_spin(&back, 0), TSpin::None);
+        assert_eq!(t_spin(&[back[0], back[1], [-1, -1]], 0), TSpin::Mini);
+        assert_eq!(t_spin(&[back[0], [-1, -1], [1, -1]], 0), TSpin::Full);
+        assert_eq!(
+            t_spin(&[back[0], back[1], [-1, -1]], LAST_KICK),
+            TSpin::Full
+        );
+    }
+
+    #[test]
+    fn clears_score_by_lines_and_level() {
+        let score = |lines, t_spin| {
+            Scoring::default()
+                .score_lock(lines, t_spin, false, 2)
+                .points
+        };
+
+        assert_eq!(score(0, TSpin::None), 0);
+        assert_eq!(score(1, TSpin::None), 200);
+        assert_eq!(score(4, TSpin::None), 1600);
+        assert_eq!(score(0, TSpin::Mini), 200);
+        assert_eq!(score(2, TSpin::Full), 2400);
+    }
+
+    #[test]
+    fn back_to_back_survives_locks_without_lines_but_not_easy_clears() {
+        let mut scoring = Scoring::default();
+
+        assert!(!scoring.score_lock(4, TSpin::None, false, 1).back_to_back);
+        assert!(!scoring.score_lock(0, TSpin::None, false, 1).back_to_back);
+
+        let tetris = scoring.score_lock(4, TSpin::None, false, 1);
+
+        assert!(tetris.back_to_back);
+        assert_eq!(tetris.points, 1200);
+
+        scoring.score_lock(1, TSpin::None, false, 1);
+
+        assert!(!scoring.score_lock(2, TSpin::Full, false, 1).back_to_back);
+    }
+
+    #[test]
+    fn combos_count_consecutive_clears() {
+        let mut scoring = Scoring::default();
+        let mut single = || scoring.score_lock(1, TSpin::None, false, 1);
+
+        assert_eq!((single().combo, single().combo), (0, 1));
+
+        let third = single();
+
+        assert_eq!((third.combo, third.points), (2, 200));
+
+        scoring.score_lock(0, TSpin::None, false, 1);
+
+        assert_eq!(scoring.score_lock(1, TSpin::None, false, 1).combo, 0);
+    }
+
+    #[test]
+    fn perfect_clears_add_a_bonus() {
+        let mut scoring = Scoring::default();
+
+        assert_eq!(scoring.score_lock(4, TSpin::None, true, 1).points, 2800);
+        assert_eq!(scoring.score_lock(4, TSpin::None, true, 1).points, 4450);
+    }
+}
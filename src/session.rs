@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use crate::clock::FrameClock;
+use crate::game::{EndOfGame, Game};
+use crate::input::{Action, InputQueue, PAUSE_KEY};
+use crate::records::SprintResult;
+use crate::save::{save_finished_game, SaveError};
+
+/// A game being played in real time: the frame clock, the keys waiting for the next tick,
+/// pausing and what happens once the game ends. Frontends only turn their key events into
+/// key names and pass on the time between two frames.
+pub struct Session {
+    pub game: Game,
+    pub is_paused: bool,
+    pub game_over: Option<EndOfGame>,
+    /// How a finished sprint compares to the personal best.
+    pub sprint_result: Option<SprintResult>,
+    clock: FrameClock,
+    input_queue: InputQueue,
+}
+
+impl Session {
+    pub fn new(game: Game) -> Self {
+        Session {
+            game,
+            is_paused: false,
+            game_over: None,
+            sprint_result: None,
+            clock: FrameClock::default(),
+            input_queue: InputQueue::default(),
+        }
+    }
+
+    /// The pause key toggles pause, the other keys go to the input queue. Key downs are
+    /// dropped while paused or once the game is over, key ups always go through so no key
+    /// stays stuck down.
+    pub fn handle_key(&mut self, name: &str, is_down: bool) {
+        if name == PAUSE_KEY {
+            if is_down {
+                self.is_paused = !self.is_paused;
+            }
+            return;
+        }
+
+        let Some(action) = Action::from_key_name(name) else {
+            return;
+        };
+
+        match is_down {
+            true if self.is_paused || self.game_over.is_some() => {}
+            true => self.input_queue.key_down(action),
+            false => self.input_queue.key_up(action),
+        }
+    }
+
+    /// A key down followed by its key up, for frontends that cannot tell when keys are
+    /// let go.
+    pub fn tap_key(&mut self, name: &str) {
+        self.handle_key(name, true);
+        self.handle_key(name, false);
+    }
+
+    /// Ticks the game once for every whole frame in `frame_time`, the buffered inputs going
+    /// into the first of them. Nothing moves while paused or once the game is over.
+    ///
+    /// When the game ends, its replay and sprint time are saved and whatever failed to save
+    /// is returned for the frontend to report.
+    pub fn advance(&mut self, frame_time: Duration) -> Vec<SaveError> {
+        if self.is_paused || self.game_over.is_some() {
+            return Vec::new();
+        }
+
+        for _ in 0..self.clock.advance(frame_time) {
+            let inputs = self.input_queue.take();
+
+            if let Err(end_of_game) = self.game.tick(&inputs) {
+                return self.end_game(end_of_game);
+            }
+        }
+
+        // Nothing reacts to the events yet, they are only drained so they do not pile up.
+        self.game.drain_events();
+
+        Vec::new()
+    }
+
+    /// Stops the game and keeps its replay, and its time if it is a finished sprint.
+    fn end_game(&mut self, end_of_game: EndOfGame) -> Vec<SaveError> {
+        self.game_over = Some(end_of_game);
+
+        let finished_game = save_finished_game(&self.game, end_of_game);
+
+        self.sprint_result = finished_game.sprint_result;
+
+        finished_game.errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{new_game_with_seed, GameConfig};
+    use crate::input::{HeldKey, Input};
+
+    #[test]
+    fn keys_pressed_while_paused_are_dropped_but_releases_go_through() {
+        let mut session = Session::new(new_game_with_seed(GameConfig::default(), 0));
+
+        session.handle_key("Left", true);
+        session.handle_key(PAUSE_KEY, true);
+        session.handle_key(PAUSE_KEY, false);
+        session.handle_key("Right", true);
+        session.handle_key("Left", false);
+
+        assert!(session.is_paused);
+        assert_eq!(
+            session.input_queue.take(),
+            vec![Input::Press(HeldKey::Left), Input::Release(HeldKey::Left)]
+        );
+    }
+}